@@ -0,0 +1,201 @@
+//! Minimal reader for BDF (Glyph Bitmap Distribution Format) fonts
+//!
+//! Only what is needed to convert a font is read: the font ascent and descent, and the encoding, advance, bounding box
+//! and bitmap of each glyph. The source is searched for each glyph as it is needed, so nothing is allocated.
+
+use crate::Error;
+
+/// A parsed BDF font.
+#[derive(Clone, Copy, Debug)]
+pub struct BdfFont<'a> {
+    src: &'a str,
+    ascent: i32,
+    descent: i32,
+}
+
+/// A glyph from a [`BdfFont`].
+#[derive(Clone, Copy, Debug)]
+pub struct BdfGlyph<'a> {
+    advance: i32,
+    width: i32,
+    height: i32,
+    x_offset: i32,
+    y_offset: i32,
+    bitmap: &'a str,
+}
+
+impl<'a> BdfFont<'a> {
+    /// Read the font metrics from the source of a BDF font.
+    pub fn parse(src: &'a str) -> Result<Self, Error> {
+        let mut ascent = None;
+        let mut descent = None;
+        let mut bounding_box = None;
+
+        for line in src.lines() {
+            let mut words = line.split_ascii_whitespace();
+            match words.next() {
+                Some("FONT_ASCENT") => ascent = Some(number(words.next())?),
+                Some("FONT_DESCENT") => descent = Some(number(words.next())?),
+                Some("FONTBOUNDINGBOX") => bounding_box = Some(numbers::<4>(words)?),
+                // the properties come before the glyphs
+                Some("CHARS") => break,
+                _ => {}
+            }
+        }
+
+        // fall back to the bounding box if the font has no ascent or descent
+        let [_, height, _, y_offset] =
+            bounding_box.ok_or(Error::Syntax("missing FONTBOUNDINGBOX"))?;
+        Ok(Self {
+            src,
+            ascent: ascent.unwrap_or(height + y_offset),
+            descent: descent.unwrap_or(-y_offset),
+        })
+    }
+
+    /// Height of a line of text in pixels.
+    pub fn height(&self) -> i32 {
+        self.ascent + self.descent
+    }
+
+    /// Number of rows from the top of a line to the baseline.
+    pub fn baseline(&self) -> i32 {
+        self.ascent
+    }
+
+    /// Find the glyph for a character, or `None` if the font does not have it.
+    pub fn glyph(&self, character: char) -> Result<Option<BdfGlyph<'a>>, Error> {
+        let mut encoding = None;
+        let mut advance = 0;
+        let mut bounding_box = [0; 4];
+        let mut offset = 0;
+
+        for line in self.src.split_inclusive('\n') {
+            offset += line.len();
+
+            let mut words = line.split_ascii_whitespace();
+            match words.next() {
+                Some("STARTCHAR") => encoding = None,
+                Some("ENCODING") => encoding = Some(number(words.next())?),
+                Some("DWIDTH") => advance = number(words.next())?,
+                Some("BBX") => bounding_box = numbers::<4>(words)?,
+                Some("BITMAP") if encoding == Some(character as i32) => {
+                    let [width, height, x_offset, y_offset] = bounding_box;
+                    return Ok(Some(BdfGlyph {
+                        advance,
+                        width,
+                        height,
+                        x_offset,
+                        y_offset,
+                        bitmap: &self.src[offset..],
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl BdfGlyph<'_> {
+    /// Distance to the start of the next glyph in pixels.
+    pub fn advance(&self) -> i32 {
+        self.advance
+    }
+
+    /// Width of the glyph when it is placed at the origin, clipping anything to the left of the origin.
+    pub fn cell_width(&self) -> i32 {
+        (self.x_offset + self.width).max(0)
+    }
+
+    /// Whether a pixel is on, where (0, 0) is the origin at the top of the line.
+    pub fn cell_pixel(&self, font: &BdfFont, x: i32, y: i32) -> Result<bool, Error> {
+        let gx = x - self.x_offset;
+        let gy = y - (font.ascent - self.y_offset - self.height);
+        if gx < 0 || gy < 0 || gx >= self.width || gy >= self.height {
+            return Ok(false);
+        }
+
+        let row = self
+            .bitmap
+            .lines()
+            .nth(gy as usize)
+            .ok_or(Error::Syntax("glyph bitmap is too short"))?
+            .trim();
+        let byte = (gx / 8) as usize * 2;
+        let hex = row
+            .get(byte..byte + 2)
+            .ok_or(Error::Syntax("glyph bitmap row is too short"))?;
+        let bits =
+            u8::from_str_radix(hex, 16).map_err(|_| Error::Syntax("invalid glyph bitmap"))?;
+
+        Ok(bits & (0x80 >> (gx % 8)) != 0)
+    }
+}
+
+fn number(word: Option<&str>) -> Result<i32, Error> {
+    word.and_then(|w| w.parse().ok())
+        .ok_or(Error::Syntax("expected a number"))
+}
+
+fn numbers<'a, const N: usize>(
+    mut words: impl Iterator<Item = &'a str>,
+) -> Result<[i32; N], Error> {
+    let mut values = [0; N];
+    for value in values.iter_mut() {
+        *value = number(words.next())?;
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FONT: &str = "\
+FONTBOUNDINGBOX 4 6 0 -2
+CHARS 1
+STARTCHAR j
+ENCODING 106
+DWIDTH 3 0
+BBX 2 5 -1 -2
+BITMAP
+40
+00
+40
+40
+80
+ENDCHAR
+";
+
+    #[test]
+    fn metrics_fall_back_to_bounding_box() {
+        let font = BdfFont::parse(FONT).unwrap();
+        assert_eq!(font.height(), 6);
+        assert_eq!(font.baseline(), 4);
+    }
+
+    #[test]
+    fn finds_glyphs() {
+        let font = BdfFont::parse(FONT).unwrap();
+        assert!(font.glyph('i').unwrap().is_none());
+
+        let glyph = font.glyph('j').unwrap().unwrap();
+        assert_eq!(glyph.advance(), 3);
+        // the column left of the origin is clipped
+        assert_eq!(glyph.cell_width(), 1);
+    }
+
+    #[test]
+    fn places_pixels_from_the_top_of_the_line() {
+        let font = BdfFont::parse(FONT).unwrap();
+        let glyph = font.glyph('j').unwrap().unwrap();
+
+        // the glyph starts 1 row below the top and ends 2 rows below the baseline, and the hook is clipped
+        let column: [bool; 6] =
+            core::array::from_fn(|y| glyph.cell_pixel(&font, 0, y as i32).unwrap());
+        assert_eq!(column, [false, true, false, true, true, false]);
+        assert!(!glyph.cell_pixel(&font, 1, 1).unwrap());
+    }
+}
@@ -0,0 +1,393 @@
+//! Build-time conversion of BDF bitmap fonts for `arcadecoder_hw`
+//!
+//! Existing pixel fonts (like tom-thumb, 4x6 or 5x7) can be used instead of drawing glyphs by hand. [`write_font`]
+//! reads a BDF font and writes Rust source for a `static` `arcadecoder_hw::font::Font`, keeping only the characters
+//! that are needed so the font stays small in flash. PCF fonts can be converted to BDF first with `pcf2bdf`.
+//!
+//! This is intended to be used from a build script, with the generated source included in the firmware:
+//!
+//! ```no_run
+//! // build.rs
+//! let bdf = std::fs::read_to_string("fonts/tom-thumb.bdf").unwrap();
+//!
+//! let mut out = String::new();
+//! arcadecoder_fontgen::write_font(&mut out, "TOM_THUMB", &bdf, &[' '..='~']).unwrap();
+//!
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{out_dir}/fonts.rs"), out).unwrap();
+//! println!("cargo:rerun-if-changed=fonts/tom-thumb.bdf");
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/fonts.rs"));
+//!
+//! fb.draw_char('A', &TOM_THUMB, (0, 0), WHITE);
+//! ```
+//!
+//! The crate does not allocate and has no dependencies, so it builds for any target.
+
+#![no_std]
+
+pub mod bdf;
+
+use core::fmt::{self, Write};
+use core::ops::RangeInclusive;
+
+use bdf::{BdfFont, BdfGlyph};
+
+/// Path to the font types in the generated source.
+const FONT_PATH: &str = "::arcadecoder_hw::font";
+
+/// An error converting a font.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The BDF source could not be read.
+    Syntax(&'static str),
+
+    /// The font is too tall, or a glyph is too wide, for the font format.
+    TooLarge,
+
+    /// The selected glyphs do not fit in the font format.
+    TooManyGlyphs,
+
+    /// Writing the output failed.
+    Write,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(message) => write!(f, "invalid bdf font: {message}"),
+            Self::TooLarge => write!(f, "font or glyph is larger than 255 pixels"),
+            Self::TooManyGlyphs => write!(f, "too many glyphs selected"),
+            Self::Write => write!(f, "could not write output"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Self::Write
+    }
+}
+
+/// Convert a BDF font to Rust source for a `pub static` font called `name`.
+///
+/// Only the characters in `chars` are included, and characters the font does not have are skipped. The ranges should
+/// not overlap.
+pub fn write_font(
+    out: &mut impl Write,
+    name: &str,
+    bdf: &str,
+    chars: &[RangeInclusive<char>],
+) -> Result<(), Error> {
+    let font = BdfFont::parse(bdf)?;
+    let height = to_u8(font.height())?;
+    let baseline = to_u8(font.baseline())?;
+
+    writeln!(out, "/// Generated by arcadecoder_fontgen.")?;
+    writeln!(
+        out,
+        "pub static {name}: {FONT_PATH}::Font = {FONT_PATH}::Font::new("
+    )?;
+    writeln!(out, "    {height},")?;
+    writeln!(out, "    {baseline},")?;
+
+    // consecutive characters that are in the font share a range
+    writeln!(out, "    &[")?;
+    let mut index = 0_u16;
+    let mut run: Option<(char, char, u16)> = None;
+    for_each_glyph(&font, chars, |c, _| {
+        match run {
+            Some((first, last, start)) if last as u32 + 1 == c as u32 => {
+                run = Some((first, c, start))
+            }
+            _ => {
+                if let Some(range) = run {
+                    write_range(out, range)?;
+                }
+                run = Some((c, c, index));
+            }
+        }
+        index = index.checked_add(1).ok_or(Error::TooManyGlyphs)?;
+        Ok(())
+    })?;
+    if let Some(range) = run {
+        write_range(out, range)?;
+    }
+    writeln!(out, "    ],")?;
+
+    writeln!(out, "    &[")?;
+    let mut offset = 0_u16;
+    for_each_glyph(&font, chars, |c, glyph| {
+        let width = to_u8(glyph.cell_width())?;
+        let advance = to_u8(glyph.advance())?;
+        writeln!(
+            out,
+            "        {FONT_PATH}::Glyph::new({offset}, {width}, {advance}), // {c:?}"
+        )?;
+
+        let bytes = (usize::from(width) * usize::from(height)).div_ceil(8);
+        offset = u16::try_from(bytes)
+            .ok()
+            .and_then(|bytes| offset.checked_add(bytes))
+            .ok_or(Error::TooManyGlyphs)?;
+        Ok(())
+    })?;
+    writeln!(out, "    ],")?;
+
+    writeln!(out, "    &[")?;
+    for_each_glyph(&font, chars, |c, glyph| write_bitmap(out, &font, &glyph, c))?;
+    writeln!(out, "    ],")?;
+
+    writeln!(out, ");")?;
+    Ok(())
+}
+
+/// Call a function for each selected character that is in the font, in order.
+fn for_each_glyph<'a>(
+    font: &BdfFont<'a>,
+    chars: &[RangeInclusive<char>],
+    mut f: impl FnMut(char, BdfGlyph<'a>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for c in chars.iter().cloned().flatten() {
+        if let Some(glyph) = font.glyph(c)? {
+            f(c, glyph)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_range(out: &mut impl Write, (first, last, glyph): (char, char, u16)) -> Result<(), Error> {
+    writeln!(
+        out,
+        "        {FONT_PATH}::GlyphRange::new({first:?}, {last:?}, {glyph}),"
+    )?;
+    Ok(())
+}
+
+/// Write the packed bits for a glyph, one bit per pixel in rows from the top-left.
+fn write_bitmap(
+    out: &mut impl Write,
+    font: &BdfFont,
+    glyph: &BdfGlyph,
+    c: char,
+) -> Result<(), Error> {
+    let pixels = glyph.cell_width() * font.height();
+    if pixels == 0 {
+        return Ok(());
+    }
+
+    write!(out, "       ")?;
+    let mut byte = 0_u8;
+    for i in 0..pixels {
+        let (x, y) = (i % glyph.cell_width(), i / glyph.cell_width());
+        if glyph.cell_pixel(font, x, y)? {
+            byte |= 0x80 >> (i % 8);
+        }
+
+        if i % 8 == 7 || i == pixels - 1 {
+            write!(out, " 0x{byte:02x},")?;
+            byte = 0;
+        }
+    }
+    writeln!(out, " // {c:?}")?;
+    Ok(())
+}
+
+fn to_u8(value: i32) -> Result<u8, Error> {
+    u8::try_from(value).map_err(|_| Error::TooLarge)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+    use std::string::String;
+
+    /// A font with a 4 pixel ascent and 1 pixel descent. 'B' sits below the baseline, 'C' is missing and 'Z' is never
+    /// selected.
+    const FONT: &str = "\
+STARTFONT 2.1
+FONT -test-tiny
+SIZE 5 75 75
+FONTBOUNDINGBOX 3 5 0 -1
+STARTPROPERTIES 2
+FONT_ASCENT 4
+FONT_DESCENT 1
+ENDPROPERTIES
+CHARS 5
+STARTCHAR space
+ENCODING 32
+DWIDTH 3 0
+BBX 0 0 0 0
+BITMAP
+ENDCHAR
+STARTCHAR A
+ENCODING 65
+DWIDTH 4 0
+BBX 3 4 0 0
+BITMAP
+40
+A0
+E0
+A0
+ENDCHAR
+STARTCHAR B
+ENCODING 66
+DWIDTH 4 0
+BBX 2 2 1 -1
+BITMAP
+C0
+40
+ENDCHAR
+STARTCHAR D
+ENCODING 68
+DWIDTH 2 0
+BBX 1 1 0 0
+BITMAP
+80
+ENDCHAR
+STARTCHAR Z
+ENCODING 90
+DWIDTH 4 0
+BBX 3 1 0 0
+BITMAP
+E0
+ENDCHAR
+ENDFONT
+";
+
+    fn convert(bdf: &str, chars: &[RangeInclusive<char>]) -> Result<String, Error> {
+        let mut out = String::new();
+        write_font(&mut out, "TINY", bdf, chars)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn writes_font() {
+        let out = convert(FONT, &[' '..=' ', 'A'..='D']).unwrap();
+
+        // 'A' is 010 101 111 101 000, 'B' is shifted right by its offset and down below the baseline, and 'D' sits on
+        // the baseline
+        let expected = "\
+/// Generated by arcadecoder_fontgen.
+pub static TINY: ::arcadecoder_hw::font::Font = ::arcadecoder_hw::font::Font::new(
+    5,
+    4,
+    &[
+        ::arcadecoder_hw::font::GlyphRange::new(' ', ' ', 0),
+        ::arcadecoder_hw::font::GlyphRange::new('A', 'B', 1),
+        ::arcadecoder_hw::font::GlyphRange::new('D', 'D', 3),
+    ],
+    &[
+        ::arcadecoder_hw::font::Glyph::new(0, 0, 3), // ' '
+        ::arcadecoder_hw::font::Glyph::new(0, 3, 4), // 'A'
+        ::arcadecoder_hw::font::Glyph::new(2, 3, 4), // 'B'
+        ::arcadecoder_hw::font::Glyph::new(4, 1, 2), // 'D'
+    ],
+    &[
+        0x57, 0xd0, // 'A'
+        0x00, 0x32, // 'B'
+        0x10, // 'D'
+    ],
+);
+";
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn drops_unselected_glyphs() {
+        let out = convert(FONT, &['B'..='B', 'Z'..='Z']).unwrap();
+
+        assert!(out.contains("GlyphRange::new('B', 'B', 0),"));
+        assert!(out.contains("GlyphRange::new('Z', 'Z', 1),"));
+        assert!(out.contains("Glyph::new(0, 3, 4), // 'B'"));
+        assert!(out.contains("Glyph::new(2, 3, 4), // 'Z'"));
+        assert!(!out.contains("'A'"));
+        assert!(!out.contains("'D'"));
+        assert!(!out.contains("' '"));
+    }
+
+    #[test]
+    fn writes_empty_font() {
+        // nothing selected is in the font
+        let out = convert(FONT, &['a'..='z']).unwrap();
+        assert!(out.contains("    &[\n    ],\n    &[\n    ],\n    &[\n    ],\n"));
+    }
+
+    #[test]
+    fn rejects_malformed_fonts() {
+        let chars = ['A'..='A'];
+
+        let no_bounding_box = FONT.replace("FONTBOUNDINGBOX 3 5 0 -1\n", "");
+        assert_eq!(
+            convert(&no_bounding_box, &chars),
+            Err(Error::Syntax("missing FONTBOUNDINGBOX"))
+        );
+
+        let bad_ascent = FONT.replace("FONT_ASCENT 4", "FONT_ASCENT four");
+        assert_eq!(
+            convert(&bad_ascent, &chars),
+            Err(Error::Syntax("expected a number"))
+        );
+
+        let bad_bbx = FONT.replace("BBX 3 4 0 0", "BBX 3 4 0");
+        assert_eq!(
+            convert(&bad_bbx, &chars),
+            Err(Error::Syntax("expected a number"))
+        );
+
+        let bad_hex = FONT.replace("E0\nA0", "G0\nA0");
+        assert_eq!(
+            convert(&bad_hex, &chars),
+            Err(Error::Syntax("invalid glyph bitmap"))
+        );
+
+        let short_row = FONT.replace("E0\nA0", "E\nA0");
+        assert_eq!(
+            convert(&short_row, &chars),
+            Err(Error::Syntax("glyph bitmap row is too short"))
+        );
+
+        // the bitmap runs off the end of the source
+        let short_bitmap =
+            "FONTBOUNDINGBOX 3 5 0 -1\nCHARS 1\nENCODING 65\nDWIDTH 4 0\nBBX 3 4 0 0\nBITMAP\n40\n";
+        assert_eq!(
+            convert(short_bitmap, &chars),
+            Err(Error::Syntax("glyph bitmap is too short"))
+        );
+    }
+
+    #[test]
+    fn rejects_large_fonts() {
+        let tall = FONT.replace("FONT_DESCENT 1", "FONT_DESCENT 300");
+        assert_eq!(convert(&tall, &['A'..='A']), Err(Error::TooLarge));
+
+        let wide = FONT.replace("DWIDTH 4 0\nBBX 3 4", "DWIDTH 256 0\nBBX 3 4");
+        assert_eq!(convert(&wide, &['A'..='A']), Err(Error::TooLarge));
+
+        let backwards = FONT.replace("DWIDTH 4 0\nBBX 3 4", "DWIDTH -1 0\nBBX 3 4");
+        assert_eq!(convert(&backwards, &['A'..='A']), Err(Error::TooLarge));
+    }
+
+    #[test]
+    fn fails_when_output_fails() {
+        struct Full;
+
+        impl Write for Full {
+            fn write_str(&mut self, _: &str) -> fmt::Result {
+                Err(fmt::Error)
+            }
+        }
+
+        assert_eq!(
+            write_font(&mut Full, "TINY", FONT, &['A'..='A']),
+            Err(Error::Write)
+        );
+    }
+}
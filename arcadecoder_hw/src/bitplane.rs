@@ -0,0 +1,278 @@
+//! Multi-bitplane display buffer for more than 8 colors
+//!
+//! The display can only turn each LED fully on or off, so more colors are shown using binary code modulation: each
+//! bit of a color channel is stored in its own [`Framebuffer`] (a bitplane) and [`scan`] shows each bitplane for a
+//! time proportional to its weight. With [`BITPLANES`] bits per channel this gives 16 levels of red, green and blue.
+//!
+//! Set [`ScanMode::BinaryCodeModulation`] on the driver to show the [`BitplaneFramebuffer`] instead of the standard
+//! 3-bit framebuffer.
+//!
+//! [`scan`]: crate::ArcadeCoder::scan
+//! [`ScanMode::BinaryCodeModulation`]: crate::ScanMode::BinaryCodeModulation
+
+use crate::framebuffer::Framebuffer;
+use crate::geometry::Point;
+use crate::orientation::Orientation;
+use crate::Color;
+
+/// Number of bits per color channel.
+pub const BITPLANES: usize = 4;
+
+/// Maximum value of a color channel.
+pub const MAX_LEVEL: u8 = (1 << BITPLANES) - 1;
+
+/// 12-bit color with 4 bits for each of red, green and blue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rgb444 {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb444 {
+    pub const WHITE: Self = Self::new(MAX_LEVEL, MAX_LEVEL, MAX_LEVEL);
+    pub const YELLOW: Self = Self::new(MAX_LEVEL, MAX_LEVEL, 0);
+    pub const CYAN: Self = Self::new(0, MAX_LEVEL, MAX_LEVEL);
+    pub const RED: Self = Self::new(MAX_LEVEL, 0, 0);
+    pub const MAGENTA: Self = Self::new(MAX_LEVEL, 0, MAX_LEVEL);
+    pub const GREEN: Self = Self::new(0, MAX_LEVEL, 0);
+    pub const BLUE: Self = Self::new(0, 0, MAX_LEVEL);
+    pub const BLACK: Self = Self::new(0, 0, 0);
+    pub const ORANGE: Self = Self::new(MAX_LEVEL, 4, 0);
+    pub const PINK: Self = Self::new(MAX_LEVEL, 3, 6);
+    pub const PURPLE: Self = Self::new(6, 0, MAX_LEVEL);
+    pub const GREY: Self = Self::new(3, 3, 3);
+
+    /// Create a new color, with each channel clamped to [`MAX_LEVEL`].
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        const fn clamp(v: u8) -> u8 {
+            if v > MAX_LEVEL {
+                MAX_LEVEL
+            } else {
+                v
+            }
+        }
+
+        Self {
+            r: clamp(r),
+            g: clamp(g),
+            b: clamp(b),
+        }
+    }
+
+    /// Create a color from 8-bit channels, keeping the most significant bits.
+    pub const fn from_rgb888(r: u8, g: u8, b: u8) -> Self {
+        let shift = 8 - BITPLANES;
+        Self::new(r >> shift, g >> shift, b >> shift)
+    }
+
+    /// Scale the brightness of the color, where 255 leaves it unchanged.
+    pub const fn scaled(self, level: u8) -> Self {
+        const fn scale(v: u8, level: u8) -> u8 {
+            ((v as u16 * level as u16 + 127) / 255) as u8
+        }
+
+        Self::new(
+            scale(self.r, level),
+            scale(self.g, level),
+            scale(self.b, level),
+        )
+    }
+
+    /// Get the 3-bit color for a bitplane.
+    fn plane_color(self, plane: usize) -> Color {
+        Color::from_rgb(
+            (self.r >> plane) & 1 == 1,
+            (self.g >> plane) & 1 == 1,
+            (self.b >> plane) & 1 == 1,
+        )
+    }
+}
+
+impl From<Color> for Rgb444 {
+    fn from(color: Color) -> Self {
+        Self::new(
+            u8::from(color.red()) * MAX_LEVEL,
+            u8::from(color.green()) * MAX_LEVEL,
+            u8::from(color.blue()) * MAX_LEVEL,
+        )
+    }
+}
+
+/// A 12x12 display buffer with [`BITPLANES`] bits per color channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BitplaneFramebuffer {
+    planes: [Framebuffer; BITPLANES],
+}
+
+impl BitplaneFramebuffer {
+    /// Create a new blank framebuffer.
+    pub const fn new() -> Self {
+        Self {
+            planes: [Framebuffer::new(); BITPLANES],
+        }
+    }
+
+    /// Clear the framebuffer to make the screen blank.
+    pub fn clear(&mut self) {
+        for plane in self.planes.iter_mut() {
+            plane.clear();
+        }
+    }
+
+    /// Set every pixel to a color.
+    pub fn fill(&mut self, color: Rgb444) {
+        for (i, plane) in self.planes.iter_mut().enumerate() {
+            plane.fill(color.plane_color(i));
+        }
+    }
+
+    /// Set a pixel to a color
+    ///
+    /// _Indexing starts from 0, so (0, 0) is the top-left and (11, 11) is the bottom-right._
+    pub fn set_pixel(&mut self, pos: impl Into<Point>, color: Rgb444) {
+        let pos = pos.into();
+        for (i, plane) in self.planes.iter_mut().enumerate() {
+            plane.set_pixel(pos, color.plane_color(i));
+        }
+    }
+
+    /// Get the color of a pixel, or `None` if the coordinates are out of bounds.
+    pub fn get_pixel(&self, pos: impl Into<Point>) -> Option<Rgb444> {
+        let pos = pos.into().to_coordinates()?;
+
+        let mut color = Rgb444::BLACK;
+        for (i, plane) in self.planes.iter().enumerate() {
+            let plane_color = plane.get_pixel(pos)?;
+            color.r |= u8::from(plane_color.red()) << i;
+            color.g |= u8::from(plane_color.green()) << i;
+            color.b |= u8::from(plane_color.blue()) << i;
+        }
+
+        Some(color)
+    }
+
+    /// Get a copy of the framebuffer in the panel's native layout, for a frame drawn in `orientation`.
+    pub fn oriented(&self, orientation: Orientation) -> Self {
+        Self {
+            planes: self.planes.map(|plane| plane.oriented(orientation)),
+        }
+    }
+
+    /// Get a single bitplane, where plane 0 is the least significant bit.
+    pub fn plane(&self, plane: usize) -> &Framebuffer {
+        &self.planes[plane]
+    }
+}
+
+impl From<Framebuffer> for BitplaneFramebuffer {
+    /// Convert a 3-bit framebuffer, turning on each channel at full brightness.
+    fn from(fb: Framebuffer) -> Self {
+        Self {
+            planes: [fb; BITPLANES],
+        }
+    }
+}
+
+/// Get the time to show a bitplane for, so the weighted times for all planes add up to `on_time`.
+pub(crate) fn bitplane_on_time(on_time: u32, plane: usize) -> u32 {
+    on_time * (1 << plane) / u32::from(MAX_LEVEL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ArcadeCoderConfig;
+    use crate::{BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, WHITE, YELLOW};
+
+    #[test]
+    fn new_clamps_channels() {
+        assert_eq!(Rgb444::new(16, 200, 15), Rgb444::new(15, 15, 15));
+        assert_eq!(Rgb444::from_rgb888(0xff, 0x80, 0x0f), Rgb444::new(15, 8, 0));
+    }
+
+    #[test]
+    fn split_into_planes() {
+        // red 0b1010, green 0b0101, blue 0b0011
+        let mut fb = BitplaneFramebuffer::new();
+        fb.set_pixel((3, 7), Rgb444::new(0b1010, 0b0101, 0b0011));
+
+        let expected = [CYAN, MAGENTA, GREEN, RED];
+        for (i, color) in expected.into_iter().enumerate() {
+            assert_eq!(fb.plane(i).get_pixel((3, 7)), Some(color), "plane {i}");
+            assert_eq!(fb.plane(i).get_pixel((7, 3)), Some(BLACK), "plane {i}");
+        }
+    }
+
+    #[test]
+    fn get_pixel_round_trip() {
+        let mut fb = BitplaneFramebuffer::new();
+        for level in 0..=MAX_LEVEL {
+            let color = Rgb444::new(level, MAX_LEVEL - level, level / 2);
+            let pos = (usize::from(level) % 12, usize::from(level) / 12);
+            fb.set_pixel(pos, color);
+            assert_eq!(fb.get_pixel(pos), Some(color));
+        }
+
+        assert_eq!(fb.get_pixel((11, 11)), Some(Rgb444::BLACK));
+        assert_eq!(fb.get_pixel((12, 0)), None);
+        assert_eq!(fb.get_pixel((0, -1)), None);
+
+        fb.fill(Rgb444::ORANGE);
+        assert!((0..12).all(|i| fb.get_pixel((i, 11 - i)) == Some(Rgb444::ORANGE)));
+        fb.clear();
+        assert_eq!(fb, BitplaneFramebuffer::new());
+    }
+
+    #[test]
+    fn scaled() {
+        assert_eq!(Rgb444::WHITE.scaled(255), Rgb444::WHITE);
+        assert_eq!(Rgb444::WHITE.scaled(0), Rgb444::BLACK);
+        // rounded to the nearest level
+        assert_eq!(Rgb444::WHITE.scaled(128), Rgb444::new(8, 8, 8));
+        assert_eq!(Rgb444::new(15, 4, 1).scaled(127), Rgb444::new(7, 2, 0));
+        assert_eq!(Rgb444::new(15, 4, 1).scaled(128), Rgb444::new(8, 2, 1));
+    }
+
+    #[test]
+    fn from_framebuffer() {
+        let mut fb = Framebuffer::new();
+        for (i, color) in [RED, GREEN, BLUE, YELLOW, CYAN, MAGENTA, WHITE]
+            .into_iter()
+            .enumerate()
+        {
+            fb.set_pixel((i, i), color);
+        }
+
+        // every bit that is set in the framebuffer is set in every plane, so each channel is at full brightness
+        let planes = BitplaneFramebuffer::from(fb);
+        for i in 0..BITPLANES {
+            assert_eq!(*planes.plane(i), fb);
+        }
+        for ((x, y), color) in fb.pixels() {
+            assert_eq!(planes.get_pixel((x, y)), Some(Rgb444::from(color)));
+        }
+    }
+
+    #[test]
+    fn on_time_per_plane() {
+        let on_time = ArcadeCoderConfig::arcade_coder().channel_on_time();
+        let times: [u32; BITPLANES] =
+            core::array::from_fn(|plane| bitplane_on_time(on_time, plane));
+        assert_eq!(times, [92, 185, 370, 740]);
+
+        // each plane is shown twice as long as the one before, and the frame takes about as long as a 3-bit one
+        for time in [1, 15, 100, on_time, 10_000] {
+            let times: [u32; BITPLANES] =
+                core::array::from_fn(|plane| bitplane_on_time(time, plane));
+            let total: u32 = times.iter().sum();
+            assert!(
+                total <= time && time - total < BITPLANES as u32,
+                "{time}: {total}"
+            );
+            for pair in times.windows(2) {
+                assert!(pair[1].abs_diff(2 * pair[0]) <= 1, "{time}: {times:?}");
+            }
+        }
+    }
+}
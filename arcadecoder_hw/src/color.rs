@@ -0,0 +1,376 @@
+//! The 8 colors the display can show
+//!
+//! Each LED can only be fully on or off, so every pixel is one of 8 [`Color`]s. Colors from other sources are converted
+//! to the nearest one, and colors can be parsed from names or hex codes so they can be set in config files:
+//!
+//! ```
+//! use arcadecoder_hw::Color;
+//!
+//! assert_eq!("#ff0".parse(), Ok(Color::Yellow));
+//! assert_eq!("cyan".parse(), Ok(Color::Cyan));
+//! assert_eq!(Color::from(0xff8000), Color::Yellow);
+//! assert_eq!(Color::from_hue(240), Color::Blue);
+//! assert_eq!(Color::Red.invert(), Color::Cyan);
+//! ```
+//!
+//! With the `serde` feature, colors are serialized as their name and can be deserialized from a name or hex code.
+
+use core::fmt;
+use core::str::FromStr;
+
+/// A 3-bit color, with each of red, green and blue either on or off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Color {
+    #[default]
+    Black = 0b000,
+    Blue = 0b001,
+    Green = 0b010,
+    Cyan = 0b011,
+    Red = 0b100,
+    Magenta = 0b101,
+    Yellow = 0b110,
+    White = 0b111,
+}
+
+impl Color {
+    /// Every color, in the order of their bits.
+    pub const ALL: [Self; 8] = [
+        Self::Black,
+        Self::Blue,
+        Self::Green,
+        Self::Cyan,
+        Self::Red,
+        Self::Magenta,
+        Self::Yellow,
+        Self::White,
+    ];
+
+    /// Create a color from whether each channel is on.
+    pub const fn from_rgb(red: bool, green: bool, blue: bool) -> Self {
+        Self::from_bits((red as u8) << 2 | (green as u8) << 1 | blue as u8)
+    }
+
+    /// Create a color from its bits, where bit 2 is red, bit 1 is green and bit 0 is blue. Higher bits are ignored.
+    pub const fn from_bits(bits: u8) -> Self {
+        Self::ALL[(bits & 0b111) as usize]
+    }
+
+    /// Create the nearest color to a hue, saturation and value, where the hue is in degrees.
+    pub const fn from_hsv(hue: u16, saturation: u8, value: u8) -> Self {
+        let (hue, saturation, value) = (hue % 360, saturation as u32, value as u32);
+        let chroma = value * saturation / 255;
+        let min = value - chroma;
+
+        // how far through each 60 degree sector the hue is, rising in even sectors and falling in odd ones
+        let sector = hue / 60;
+        let progress = (hue % 60) as u32;
+        let mid = if sector % 2 == 0 {
+            chroma * progress / 60
+        } else {
+            chroma * (60 - progress) / 60
+        };
+
+        let (r, g, b) = match sector {
+            0 => (chroma, mid, 0),
+            1 => (mid, chroma, 0),
+            2 => (0, chroma, mid),
+            3 => (0, mid, chroma),
+            4 => (mid, 0, chroma),
+            _ => (chroma, 0, mid),
+        };
+        Self::from_rgb888((r + min) as u8, (g + min) as u8, (b + min) as u8)
+    }
+
+    /// Create the nearest fully saturated, full brightness color to a hue in degrees.
+    pub const fn from_hue(hue: u16) -> Self {
+        Self::from_hsv(hue, 255, 255)
+    }
+
+    /// Create the nearest color to a 24-bit color, by turning on each channel that is at least half brightness.
+    pub const fn from_rgb888(r: u8, g: u8, b: u8) -> Self {
+        Self::from_rgb(r >= 0x80, g >= 0x80, b >= 0x80)
+    }
+
+    /// Get the color as 24-bit red, green and blue channels.
+    pub const fn to_rgb888(self) -> (u8, u8, u8) {
+        const fn channel(on: bool) -> u8 {
+            if on {
+                0xff
+            } else {
+                0
+            }
+        }
+
+        (
+            channel(self.red()),
+            channel(self.green()),
+            channel(self.blue()),
+        )
+    }
+
+    /// The bits of the color, where bit 2 is red, bit 1 is green and bit 0 is blue.
+    pub const fn bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether the red channel is on.
+    pub const fn red(self) -> bool {
+        self.bits() & 0b100 != 0
+    }
+
+    /// Whether the green channel is on.
+    pub const fn green(self) -> bool {
+        self.bits() & 0b010 != 0
+    }
+
+    /// Whether the blue channel is on.
+    pub const fn blue(self) -> bool {
+        self.bits() & 0b001 != 0
+    }
+
+    /// Get the opposite color, with every channel switched.
+    pub const fn invert(self) -> Self {
+        Self::from_bits(!self.bits())
+    }
+
+    /// The lowercase name of the color.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Black => "black",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Cyan => "cyan",
+            Self::Red => "red",
+            Self::Magenta => "magenta",
+            Self::Yellow => "yellow",
+            Self::White => "white",
+        }
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    /// Convert 24-bit red, green and blue channels to the nearest color.
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Self::from_rgb888(r, g, b)
+    }
+}
+
+impl From<u32> for Color {
+    /// Convert a 24-bit color written as `0xRRGGBB` to the nearest color. The top byte is ignored.
+    fn from(rgb: u32) -> Self {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        Self::from_rgb888(r, g, b)
+    }
+}
+
+impl From<(bool, bool, bool)> for Color {
+    /// Convert whether each of red, green and blue is on.
+    fn from((red, green, blue): (bool, bool, bool)) -> Self {
+        Self::from_rgb(red, green, blue)
+    }
+}
+
+impl From<Color> for (bool, bool, bool) {
+    fn from(color: Color) -> Self {
+        (color.red(), color.green(), color.blue())
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    /// Parse a color name like `red` (in any case), or a hex code like `#f00` or `#ff0000` which is converted to the
+    /// nearest color.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(hex) = s.strip_prefix('#') {
+            if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(ParseColorError);
+            }
+
+            let channel =
+                |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| ParseColorError);
+            return match hex.len() {
+                // each digit is repeated, so f becomes ff
+                3 => Ok(Self::from_rgb888(
+                    channel(&hex[0..1])? * 0x11,
+                    channel(&hex[1..2])? * 0x11,
+                    channel(&hex[2..3])? * 0x11,
+                )),
+                6 => Ok(Self::from_rgb888(
+                    channel(&hex[0..2])?,
+                    channel(&hex[2..4])?,
+                    channel(&hex[4..6])?,
+                )),
+                _ => Err(ParseColorError),
+            };
+        }
+
+        Self::ALL
+            .into_iter()
+            .find(|color| color.name().eq_ignore_ascii_case(s))
+            .ok_or(ParseColorError)
+    }
+}
+
+/// The error when a string is not a color name or hex code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParseColorError;
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected a color name or hex code like #ff0")
+    }
+}
+
+impl core::error::Error for ParseColorError {}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Color {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ColorVisitor;
+
+        impl serde::de::Visitor<'_> for ColorVisitor {
+            type Value = Color;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a color name or hex code")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Color, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+            }
+        }
+
+        deserializer.deserialize_str(ColorVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!("#ff0".parse(), Ok(Color::Yellow));
+        assert_eq!("#ffff00".parse(), Ok(Color::Yellow));
+        assert_eq!("#FFFF00".parse(), Ok(Color::Yellow));
+        assert_eq!("#000".parse(), Ok(Color::Black));
+
+        // each channel is rounded to the nearest
+        assert_eq!("#808080".parse(), Ok(Color::White));
+        assert_eq!("#7f7f7f".parse(), Ok(Color::Black));
+        assert_eq!("#8f0".parse(), Ok(Color::Yellow));
+        assert_eq!("#70f".parse(), Ok(Color::Blue));
+        assert_eq!(" #0ff\n".parse(), Ok(Color::Cyan));
+    }
+
+    #[test]
+    fn parse_names() {
+        for color in Color::ALL {
+            assert_eq!(color.name().parse(), Ok(color));
+        }
+        assert_eq!("Magenta".parse(), Ok(Color::Magenta));
+        assert_eq!("WHITE".parse(), Ok(Color::White));
+        assert_eq!(" red ".parse(), Ok(Color::Red));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for s in [
+            "", "#", "#ff", "#ff00", "#fffff", "#ff00000", "#ggg", "#+ff", "#-f0000", "ff0",
+            "#\u{e9}f", "orange", "re d", "redd",
+        ] {
+            assert_eq!(s.parse::<Color>(), Err(ParseColorError), "{s:?}");
+        }
+    }
+
+    #[test]
+    fn from_u32() {
+        assert_eq!(Color::from(0xff0000), Color::Red);
+        assert_eq!(Color::from(0x00ff00), Color::Green);
+        assert_eq!(Color::from(0x0000ff), Color::Blue);
+        assert_eq!(Color::from(0xff8000), Color::Yellow);
+        assert_eq!(Color::from(0x7f7f7f), Color::Black);
+        assert_eq!(Color::from(0x808080), Color::White);
+
+        // the top byte is ignored
+        assert_eq!(Color::from(0xff000000), Color::Black);
+        assert_eq!(Color::from(0x12ff00ff), Color::Magenta);
+    }
+
+    #[test]
+    fn from_rgb888() {
+        assert_eq!(Color::from((0xff, 0x7f, 0x00)), Color::Red);
+        assert_eq!(Color::from((0xff, 0x80, 0x00)), Color::Yellow);
+        assert_eq!(Color::from((0x80, 0x80, 0x80)), Color::White);
+        assert_eq!(Color::from((0x7f, 0x7f, 0x7f)), Color::Black);
+
+        // every color survives a round trip
+        for color in Color::ALL {
+            assert_eq!(Color::from(color.to_rgb888()), color);
+            let (r, g, b) = color.to_rgb888();
+            assert_eq!(Color::from(u32::from_be_bytes([0, r, g, b])), color);
+            assert_eq!(Color::from(<(bool, bool, bool)>::from(color)), color);
+        }
+    }
+
+    #[test]
+    fn from_hsv() {
+        let hues = [
+            (0, Color::Red),
+            (60, Color::Yellow),
+            (120, Color::Green),
+            (180, Color::Cyan),
+            (240, Color::Blue),
+            (300, Color::Magenta),
+            (360, Color::Red),
+            (420, Color::Yellow),
+        ];
+        for (hue, color) in hues {
+            assert_eq!(Color::from_hue(hue), color, "{hue}");
+        }
+
+        // the hue is rounded to the nearest color, with the middle of each sector rounding down
+        assert_eq!(Color::from_hue(30), Color::Red);
+        assert_eq!(Color::from_hue(31), Color::Yellow);
+        assert_eq!(Color::from_hue(89), Color::Yellow);
+        assert_eq!(Color::from_hue(90), Color::Green);
+        assert_eq!(Color::from_hue(u16::MAX), Color::Red);
+
+        // low saturation is nearer white, and low value is nearer black
+        assert_eq!(Color::from_hsv(0, 0, 255), Color::White);
+        assert_eq!(Color::from_hsv(0, 100, 255), Color::White);
+        assert_eq!(Color::from_hsv(0, 128, 255), Color::Red);
+        assert_eq!(Color::from_hsv(120, 255, 127), Color::Black);
+        assert_eq!(Color::from_hsv(120, 255, 128), Color::Green);
+        assert_eq!(Color::from_hsv(200, 0, 0), Color::Black);
+    }
+
+    #[test]
+    fn bits_and_invert() {
+        for (bits, color) in Color::ALL.into_iter().enumerate() {
+            assert_eq!(color.bits(), bits as u8);
+            assert_eq!(Color::from_bits(bits as u8 | 0b1000), color);
+            assert_eq!(color.invert().invert(), color);
+            assert_eq!(color.invert().bits(), 0b111 - color.bits());
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Driver configuration for timings, SPI rate and channel wiring
+//!
+//! [`ArcadeCoderConfig::arcade_coder`] (also the [`Default`]) matches the original Arcade Coder board. Board revisions
+//! or clones with different timings or multiplexer wiring can be supported by changing the relevant settings:
+//!
+//! ```
+//! use arcadecoder_hw::config::ArcadeCoderConfig;
+//!
+//! let config = ArcadeCoderConfig::default()
+//!     .with_spi_frequency(4_000_000)
+//!     .with_latch_delay(4)
+//!     .with_channel_map([0, 1, 2, 3, 4, 5])
+//!     .with_input_channel(7);
+//!
+//! assert!(config.validate().is_ok());
+//! ```
+//!
+//! The configuration is checked when it is applied to the driver with
+//! [`ArcadeCoder::apply_config`](crate::ArcadeCoder::apply_config).
+
+use core::fmt;
+
+use crate::framebuffer::CHANNELS;
+use crate::orientation::Orientation;
+
+/// Number of addresses that can be selected on the ICN2012 multiplexer with the A0, A1 and A2 pins.
+pub const MUX_ADDRESSES: u8 = 8;
+
+/// Settings for the [`ArcadeCoder`](crate::ArcadeCoder) driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArcadeCoderConfig {
+    spi_frequency: u32,
+    channel_select_delay: u32,
+    latch_delay: u32,
+    channel_on_time: u32,
+    debounce_reads: u8,
+    reduce_ghosting: bool,
+    channel_map: [u8; CHANNELS],
+    input_channel: u8,
+    orientation: Orientation,
+}
+
+impl Default for ArcadeCoderConfig {
+    fn default() -> Self {
+        Self::arcade_coder()
+    }
+}
+
+impl ArcadeCoderConfig {
+    /// Settings for the original Arcade Coder board.
+    pub const fn arcade_coder() -> Self {
+        Self {
+            spi_frequency: 8_000_000,
+            channel_select_delay: 3,
+            latch_delay: 2,
+            channel_on_time: 1388,
+            debounce_reads: 5,
+            reduce_ghosting: false,
+            channel_map: [0b010, 0b011, 0b101, 0b100, 0b001, 0b110],
+            input_channel: 0b000,
+            orientation: Orientation::NATIVE,
+        }
+    }
+
+    /// Set the SPI clock frequency in Hz.
+    ///
+    /// This is only used when the driver creates the SPI bus, like `ArcadeCoder::from_peripherals` on the ESP32. When
+    /// using [`ArcadeCoder::from_hal`](crate::ArcadeCoder::from_hal), set up the bus at this rate instead.
+    pub const fn with_spi_frequency(mut self, hz: u32) -> Self {
+        self.spi_frequency = hz;
+        self
+    }
+
+    /// Set the time to wait after switching channels for inputs to settle in microseconds.
+    pub const fn with_channel_select_delay(mut self, us: u32) -> Self {
+        self.channel_select_delay = us;
+        self
+    }
+
+    /// Set the time to wait after latching in microseconds.
+    pub const fn with_latch_delay(mut self, us: u32) -> Self {
+        self.latch_delay = us;
+        self
+    }
+
+    /// Set the time each channel is shown for in microseconds.
+    pub const fn with_channel_on_time(mut self, us: u32) -> Self {
+        self.channel_on_time = us;
+        self
+    }
+
+    /// Set the number of reads required for a button press to register.
+    ///
+    /// A button must read the same way for more than this many scans, so it must not be zero or a single bad read
+    /// would be reported as a press.
+    pub const fn with_debounce_reads(mut self, reads: u8) -> Self {
+        self.debounce_reads = reads;
+        self
+    }
+
+    /// Draw blank frames after writing data to reduce ghosting. Disabled by default as it slows down the scan cycle,
+    /// only enable if needed.
+    pub const fn with_reduce_ghosting(mut self, reduce_ghosting: bool) -> Self {
+        self.reduce_ghosting = reduce_ghosting;
+        self
+    }
+
+    /// Set the multiplexer address for each display channel, where bit 0 is A0, bit 1 is A1 and bit 2 is A2.
+    ///
+    /// Channel 0 lights rows 1 and 7, channel 1 lights rows 2 and 8 and so on.
+    pub const fn with_channel_map(mut self, map: [u8; CHANNELS]) -> Self {
+        self.channel_map = map;
+        self
+    }
+
+    /// Set the multiplexer address selected while scanning the buttons, with the same bit order as
+    /// [`with_channel_map`](Self::with_channel_map).
+    pub const fn with_input_channel(mut self, address: u8) -> Self {
+        self.input_channel = address;
+        self
+    }
+
+    /// Set how the application's coordinates map on to the panel, for boards that are not mounted upright. This
+    /// applies to drawing and to button events.
+    pub const fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// The SPI clock frequency in Hz.
+    pub const fn spi_frequency(&self) -> u32 {
+        self.spi_frequency
+    }
+
+    /// The time to wait after switching channels for inputs to settle in microseconds.
+    pub const fn channel_select_delay(&self) -> u32 {
+        self.channel_select_delay
+    }
+
+    /// The time to wait after latching in microseconds.
+    pub const fn latch_delay(&self) -> u32 {
+        self.latch_delay
+    }
+
+    /// The time each channel is shown for in microseconds.
+    pub const fn channel_on_time(&self) -> u32 {
+        self.channel_on_time
+    }
+
+    /// The number of reads required for a button press to register.
+    pub const fn debounce_reads(&self) -> u8 {
+        self.debounce_reads
+    }
+
+    /// Whether blank frames are drawn after writing data to reduce ghosting.
+    pub const fn reduce_ghosting(&self) -> bool {
+        self.reduce_ghosting
+    }
+
+    /// The multiplexer address for each display channel.
+    pub const fn channel_map(&self) -> [u8; CHANNELS] {
+        self.channel_map
+    }
+
+    /// The multiplexer address selected while scanning the buttons.
+    pub const fn input_channel(&self) -> u8 {
+        self.input_channel
+    }
+
+    /// How the application's coordinates map on to the panel.
+    pub const fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Get the multiplexer address for a display channel, or the input channel for `None`.
+    pub(crate) fn mux_address(&self, channel: Option<usize>) -> u8 {
+        match channel {
+            Some(channel) => self.channel_map[channel],
+            None => self.input_channel,
+        }
+    }
+
+    /// Check the settings are usable.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.spi_frequency == 0 {
+            return Err(ConfigError::SpiFrequency);
+        }
+
+        if self.channel_on_time == 0 {
+            return Err(ConfigError::ChannelOnTime);
+        }
+
+        if self.debounce_reads == 0 {
+            return Err(ConfigError::DebounceReads);
+        }
+
+        // every channel and the input channel need their own address
+        let mut used = [false; MUX_ADDRESSES as usize];
+        for address in self.channel_map.into_iter().chain([self.input_channel]) {
+            if address >= MUX_ADDRESSES {
+                return Err(ConfigError::InvalidAddress(address));
+            }
+            if used[address as usize] {
+                return Err(ConfigError::DuplicateAddress(address));
+            }
+            used[address as usize] = true;
+        }
+
+        Ok(())
+    }
+}
+
+/// A problem with an [`ArcadeCoderConfig`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ConfigError {
+    /// The SPI frequency is zero.
+    SpiFrequency,
+
+    /// The channel on time is zero.
+    ChannelOnTime,
+
+    /// The number of debounce reads is zero.
+    DebounceReads,
+
+    /// A multiplexer address does not fit in the three address pins.
+    InvalidAddress(u8),
+
+    /// A multiplexer address is used for more than one channel.
+    DuplicateAddress(u8),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SpiFrequency => write!(f, "spi frequency must not be zero"),
+            Self::ChannelOnTime => write!(f, "channel on time must not be zero"),
+            Self::DebounceReads => write!(f, "debounce reads must not be zero"),
+            Self::InvalidAddress(address) => {
+                write!(f, "multiplexer address {address} is too large")
+            }
+            Self::DuplicateAddress(address) => {
+                write!(f, "multiplexer address {address} is used more than once")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_is_valid() {
+        assert_eq!(ArcadeCoderConfig::default().validate(), Ok(()));
+        assert_eq!(
+            ArcadeCoderConfig::default()
+                .with_channel_map([0, 1, 2, 3, 4, 5])
+                .with_input_channel(7)
+                .validate(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_zero_timings() {
+        let config = ArcadeCoderConfig::default();
+        assert_eq!(
+            config.with_spi_frequency(0).validate(),
+            Err(ConfigError::SpiFrequency)
+        );
+        assert_eq!(
+            config.with_channel_on_time(0).validate(),
+            Err(ConfigError::ChannelOnTime)
+        );
+        assert_eq!(
+            config.with_debounce_reads(0).validate(),
+            Err(ConfigError::DebounceReads)
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_addresses() {
+        let config = ArcadeCoderConfig::default();
+        assert_eq!(
+            config.with_channel_map([0, 1, 2, 3, 4, 8]).validate(),
+            Err(ConfigError::InvalidAddress(8))
+        );
+        assert_eq!(
+            config.with_input_channel(255).validate(),
+            Err(ConfigError::InvalidAddress(255))
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_addresses() {
+        let config = ArcadeCoderConfig::default();
+        assert_eq!(
+            config.with_channel_map([0, 1, 2, 3, 4, 1]).validate(),
+            Err(ConfigError::DuplicateAddress(1))
+        );
+
+        // the input channel cannot share an address with a display channel either
+        assert_eq!(
+            config.with_input_channel(0b010).validate(),
+            Err(ConfigError::DuplicateAddress(0b010))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Front and back buffer pair for tear-free rendering
+//!
+//! Drawing is done on the back buffer while the display only ever shows the front buffer. Calling
+//! [`DoubleBuffer::present`] requests a swap, which happens at the start of the next frame so a partially drawn frame
+//! is never shown.
+//!
+//! ## Example
+//! ```
+//! use arcadecoder_hw::{double_buffer::DoubleBuffer, framebuffer::Framebuffer, RED};
+//!
+//! let mut buffers = DoubleBuffer::new(Framebuffer::new());
+//!
+//! let back = buffers.back_mut();
+//! back.clear();
+//! back.set_pixel((0, 0), RED);
+//! buffers.present();
+//! ```
+
+/// A pair of buffers where one is shown while the other is drawn to.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DoubleBuffer<T> {
+    buffers: [T; 2],
+    front: usize,
+    pending: bool,
+}
+
+impl<T: Copy> DoubleBuffer<T> {
+    /// Create a new double buffer, with both buffers starting as `initial`.
+    pub const fn new(initial: T) -> Self {
+        Self {
+            buffers: [initial; 2],
+            front: 0,
+            pending: false,
+        }
+    }
+
+    /// Get the buffer that is currently being shown.
+    pub fn front(&self) -> &T {
+        &self.buffers[self.front]
+    }
+
+    /// Get the buffer that is being drawn to.
+    pub fn back(&self) -> &T {
+        &self.buffers[1 - self.front]
+    }
+
+    /// Get mutable access to the buffer that is being drawn to.
+    ///
+    /// After a swap the back buffer holds a copy of the frame being shown, so it can be updated incrementally.
+    pub fn back_mut(&mut self) -> &mut T {
+        &mut self.buffers[1 - self.front]
+    }
+
+    /// Request the back buffer to be shown from the start of the next frame.
+    pub fn present(&mut self) {
+        self.pending = true;
+    }
+
+    /// Whether [`present`](Self::present) has been called and the swap has not happened yet.
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Swap the buffers if a swap has been requested. This should only be called at a frame boundary.
+    ///
+    /// Returns whether the buffers were swapped.
+    pub fn swap_if_pending(&mut self) -> bool {
+        if !self.pending {
+            return false;
+        }
+
+        self.front = 1 - self.front;
+        self.buffers[1 - self.front] = self.buffers[self.front];
+        self.pending = false;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+    use crate::{BLUE, RED};
+
+    fn red_dot() -> Framebuffer {
+        let mut fb = Framebuffer::new();
+        fb.set_pixel((0, 0), RED);
+        fb
+    }
+
+    #[test]
+    fn present_then_swap_shows_back_buffer() {
+        let mut buffers = DoubleBuffer::new(Framebuffer::new());
+        *buffers.back_mut() = red_dot();
+
+        // nothing changes until the swap
+        buffers.present();
+        assert!(buffers.is_pending());
+        assert_eq!(*buffers.front(), Framebuffer::new());
+
+        assert!(buffers.swap_if_pending());
+        assert!(!buffers.is_pending());
+        assert_eq!(*buffers.front(), red_dot());
+    }
+
+    #[test]
+    fn swap_without_present_does_nothing() {
+        let mut buffers = DoubleBuffer::new(Framebuffer::new());
+        *buffers.back_mut() = red_dot();
+
+        assert!(!buffers.swap_if_pending());
+        assert_eq!(*buffers.front(), Framebuffer::new());
+        assert_eq!(*buffers.back(), red_dot());
+
+        // a swap only happens once for each present
+        buffers.present();
+        assert!(buffers.swap_if_pending());
+        assert!(!buffers.swap_if_pending());
+        assert_eq!(*buffers.front(), red_dot());
+    }
+
+    #[test]
+    fn back_buffer_is_copy_of_front_after_swap() {
+        let mut buffers = DoubleBuffer::new(Framebuffer::new());
+        *buffers.back_mut() = red_dot();
+        buffers.present();
+        buffers.swap_if_pending();
+        assert_eq!(*buffers.back(), red_dot());
+
+        // drawing on top of the copy does not change what is shown
+        buffers.back_mut().set_pixel((1, 1), BLUE);
+        assert_eq!(*buffers.front(), red_dot());
+
+        let mut expected = red_dot();
+        expected.set_pixel((1, 1), BLUE);
+        buffers.present();
+        buffers.swap_if_pending();
+        assert_eq!(*buffers.front(), expected);
+        assert_eq!(*buffers.back(), expected);
+    }
+}
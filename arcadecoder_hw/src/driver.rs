@@ -0,0 +1,1110 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin, PinState};
+
+use crate::bitplane::{bitplane_on_time, BitplaneFramebuffer, BITPLANES};
+use crate::config::ArcadeCoderConfig;
+use crate::double_buffer::DoubleBuffer;
+use crate::framebuffer::{get_display_indexes, Framebuffer, Row, CHANNELS, ROW_BYTES};
+use crate::spi::DisplaySpi;
+use crate::{ButtonEvent, Coordinates, Error};
+
+/// How the display is driven by [`ArcadeCoder::scan`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Show the front buffer of the 3-bit [`ArcadeCoder::framebuffer`], with each channel shown once per scan.
+    #[default]
+    Standard,
+
+    /// Show the front buffer of the [`ArcadeCoder::bitplanes`] using binary code modulation, splitting each channel's
+    /// on time between the bitplanes. This allows more colors at the cost of more SPI writes per scan.
+    BinaryCodeModulation,
+}
+
+/// How the buttons are scanned by [`ArcadeCoder::scan`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputScanMode {
+    /// Send one test pattern per column for the top and bottom halves of the matrix, reading all six input rows at
+    /// once. The 24 test patterns are spread across the channels, so each channel sends 4.
+    ///
+    /// _This relies on all six input rows being valid in a single multiplexer state, which has not been validated on
+    /// hardware yet, so it is not the default._
+    AllRows,
+
+    /// Send a test pattern for every button, reading only the input row for the current channel. Each channel sends
+    /// 24 test patterns, 144 in total for each scan.
+    #[default]
+    PerChannel,
+}
+
+/// The next step of [`ArcadeCoder::scan_tick`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TickStep {
+    /// Scan the buttons for the channel that is shown, then show the next channel.
+    Channel,
+
+    /// Disable the outputs for the rest of a row's on time, to dim the display.
+    Blank { channel: usize, row: usize },
+
+    /// Show the next bitplane of a channel.
+    Row { channel: usize, row: usize },
+}
+
+/// Driver for the Arcade Coder display and buttons.
+///
+/// The driver is generic over the [`embedded_hal`] traits so it can be used with any HAL, or with mock pins and SPI on
+/// a host. The SPI bus can be any [`SpiBus`](embedded_hal::spi::SpiBus), or anything else implementing
+/// [`DisplaySpi`]. On the ESP32, [`ArcadeCoder::new`] sets up the peripherals for you.
+pub struct ArcadeCoder<SPI, O, I, D> {
+    spi: SPI,
+    pin_a0: O,
+    pin_a1: O,
+    pin_a2: O,
+    pin_oe: O,
+    pin_latch: O,
+    rows: [I; CHANNELS],
+    config: ArcadeCoderConfig,
+
+    /// The framebuffers for the display. Draw to the back buffer and call [`DoubleBuffer::present`] to show it from
+    /// the next [`scan`].
+    ///
+    /// [`scan`]: #method.scan
+    pub framebuffer: DoubleBuffer<Framebuffer>,
+
+    /// The multi-bitplane framebuffers that are shown on the display by [`scan`] when using
+    /// [`ScanMode::BinaryCodeModulation`].
+    ///
+    /// [`scan`]: #method.scan
+    pub bitplanes: DoubleBuffer<BitplaneFramebuffer>,
+
+    /// Which framebuffer to show and how.
+    pub scan_mode: ScanMode,
+
+    /// How the buttons are scanned.
+    pub input_scan_mode: InputScanMode,
+
+    /// A matrix of button presses corresponding to the physical layout, in the panel's native orientation.
+    pub button_presses: [[bool; 12]; 12],
+
+    /// The brightness of the display, from 0 (off) to 255 (full brightness).
+    ///
+    /// This shortens the time the outputs are enabled for within each channel's on time, so the scan timing and button
+    /// inputs are not affected.
+    pub brightness: u8,
+
+    prev_read: [[bool; 12]; 12],
+    stable_count: [[u8; 12]; 12],
+    stable_state: [[bool; 12]; 12],
+    // front buffers transformed to the native orientation
+    native: Framebuffer,
+    native_bitplanes: BitplaneFramebuffer,
+    next_channel: usize,
+    shown_channel: Option<usize>,
+    tick_step: TickStep,
+    tick_delay: u32,
+    delay: D,
+}
+
+impl<SPI, O, I, D> ArcadeCoder<SPI, O, I, D>
+where
+    SPI: DisplaySpi,
+    O: OutputPin,
+    I: InputPin,
+    D: DelayNs,
+{
+    /// Create a new instance of the Arcade Coder from [`embedded_hal`] peripherals.
+    ///
+    /// The SPI bus should be set up for mode 0, most significant bit first, at around 8MHz. The inputs are the six
+    /// button rows in order (rows 1/7, 2/8 and so on up to 6/12) and need pull-ups enabled.
+    ///
+    /// This uses the settings for the original board, call [`apply_config`] to change them.
+    ///
+    /// Returns an error if the pins could not be set to their initial states.
+    ///
+    /// [`apply_config`]: #method.apply_config
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_hal(
+        spi: SPI,
+        mut pin_a0: O,
+        mut pin_a1: O,
+        mut pin_a2: O,
+        mut pin_oe: O,
+        mut pin_latch: O,
+        inputs: [I; CHANNELS],
+        delay: D,
+    ) -> Result<Self, Error> {
+        pin_a0.set_low().map_err(Error::pin)?;
+        pin_a1.set_low().map_err(Error::pin)?;
+        pin_a2.set_low().map_err(Error::pin)?;
+        pin_oe.set_high().map_err(Error::pin)?;
+        pin_latch.set_low().map_err(Error::pin)?;
+
+        let config = ArcadeCoderConfig::arcade_coder();
+        Ok(Self {
+            // Display
+            spi,
+            pin_a0,
+            pin_a1,
+            pin_a2,
+            pin_oe,
+            pin_latch,
+            config,
+            framebuffer: DoubleBuffer::new(Framebuffer::new()),
+            bitplanes: DoubleBuffer::new(BitplaneFramebuffer::new()),
+            scan_mode: ScanMode::Standard,
+            input_scan_mode: InputScanMode::PerChannel,
+            brightness: u8::MAX,
+
+            // Input
+            rows: inputs,
+            button_presses: [[false; 12]; 12],
+            prev_read: [[false; 12]; 12],
+            stable_count: [[0u8; 12]; 12],
+            stable_state: [[false; 12]; 12],
+
+            native: Framebuffer::new(),
+            native_bitplanes: BitplaneFramebuffer::new(),
+            next_channel: 0,
+            shown_channel: None,
+            tick_step: TickStep::Channel,
+            tick_delay: config.channel_on_time(),
+            delay,
+        })
+    }
+
+    /// Get the current settings.
+    pub fn config(&self) -> &ArcadeCoderConfig {
+        &self.config
+    }
+
+    /// Change the settings, returning an error and keeping the current settings if they are invalid.
+    ///
+    /// The SPI frequency is not changed, as the bus has already been created.
+    pub fn apply_config(&mut self, config: &ArcadeCoderConfig) -> Result<(), Error> {
+        config.validate().map_err(|e| Error::Config(e).reported())?;
+
+        let reorient = config.orientation() != self.config.orientation();
+        self.config = *config;
+        if reorient {
+            self.update_native();
+        }
+        Ok(())
+    }
+
+    // MARK: - Display
+
+    /// Select a display channel on the multiplexer, or the input channel for `None`.
+    fn set_channel(&mut self, channel: Option<usize>) -> Result<(), Error> {
+        let address = self.config.mux_address(channel);
+
+        self.pin_a0
+            .set_state(PinState::from(address & 0b001 != 0))
+            .map_err(Error::pin)?;
+        self.pin_a1
+            .set_state(PinState::from(address & 0b010 != 0))
+            .map_err(Error::pin)?;
+        self.pin_a2
+            .set_state(PinState::from(address & 0b100 != 0))
+            .map_err(Error::pin)?;
+        // short delay for the output to stabilize
+        self.delay.delay_us(self.config.channel_select_delay());
+        Ok(())
+    }
+
+    /// Replace the SPI bus, keeping all other settings and state.
+    #[cfg(feature = "dma")]
+    pub(crate) fn map_spi<S>(self, f: impl FnOnce(SPI) -> S) -> ArcadeCoder<S, O, I, D> {
+        ArcadeCoder {
+            spi: f(self.spi),
+            pin_a0: self.pin_a0,
+            pin_a1: self.pin_a1,
+            pin_a2: self.pin_a2,
+            pin_oe: self.pin_oe,
+            pin_latch: self.pin_latch,
+            rows: self.rows,
+            config: self.config,
+            framebuffer: self.framebuffer,
+            bitplanes: self.bitplanes,
+            scan_mode: self.scan_mode,
+            input_scan_mode: self.input_scan_mode,
+            button_presses: self.button_presses,
+            brightness: self.brightness,
+            prev_read: self.prev_read,
+            stable_count: self.stable_count,
+            stable_state: self.stable_state,
+            native: self.native,
+            native_bitplanes: self.native_bitplanes,
+            next_channel: self.next_channel,
+            shown_channel: self.shown_channel,
+            tick_step: self.tick_step,
+            tick_delay: self.tick_delay,
+            delay: self.delay,
+        }
+    }
+
+    /// Show a channel on the display, waiting for its on time. The data for the first input test is queued before
+    /// returning.
+    fn display_channel(&mut self, channel: usize) -> Result<(), Error> {
+        // queue the first row so it is shifted out while the channel is selected
+        let first = self.channel_row(channel, 0);
+        self.queue_display_data(&first)?;
+        self.set_channel(Some(channel))?;
+
+        for row in 0..self.display_rows() {
+            let (on_time, lit_time) = self.show_row(channel, row)?;
+
+            // wait a short duration, then disable the outputs (oe is active low) for the rest of the on time to dim
+            // the display
+            self.delay.delay_us(lit_time);
+            self.pin_oe.set_high().map_err(Error::pin)?;
+            self.delay.delay_us(on_time - lit_time);
+        }
+
+        Ok(())
+    }
+
+    /// Get the number of rows of data shown for each channel.
+    fn display_rows(&self) -> usize {
+        match self.scan_mode {
+            ScanMode::Standard => 1,
+            ScanMode::BinaryCodeModulation => BITPLANES,
+        }
+    }
+
+    /// Show a row of a channel that has already been queued, then queue the data that follows it.
+    ///
+    /// Returns the time to show the row for, and the part of that time to leave the outputs enabled for.
+    fn show_row(&mut self, channel: usize, row: usize) -> Result<(u32, u32), Error> {
+        self.latch_display_data()?;
+
+        // queue the next data while this row is lit
+        let next = if row + 1 < self.display_rows() {
+            self.channel_row(channel, row + 1)
+        } else {
+            self.first_input_data(channel)
+        };
+        self.queue_display_data(&next)?;
+        self.delay.delay_us(self.config.latch_delay());
+
+        Ok(self.row_times(row))
+    }
+
+    /// Get the time to show a row for, and the part of that time to leave the outputs enabled for at the current
+    /// brightness.
+    fn row_times(&self, row: usize) -> (u32, u32) {
+        let on_time = match self.scan_mode {
+            ScanMode::Standard => self.config.channel_on_time(),
+            // show each bitplane for a time proportional to its weight
+            ScanMode::BinaryCodeModulation => bitplane_on_time(self.config.channel_on_time(), row),
+        };
+        let lit_time =
+            (u64::from(on_time) * u64::from(self.brightness) / u64::from(u8::MAX)) as u32;
+        (on_time, lit_time)
+    }
+
+    /// Get the data for a channel from the front buffer. `index` is the bitplane when using binary code modulation.
+    fn channel_row(&self, channel: usize, index: usize) -> Row {
+        match self.scan_mode {
+            ScanMode::Standard => *self.native.row(channel),
+            ScanMode::BinaryCodeModulation => *self.native_bitplanes.plane(index).row(channel),
+        }
+    }
+
+    /// Show any newly presented frames. This should only be called at a frame boundary.
+    fn swap_buffers(&mut self) {
+        // swap both, as either could have been presented
+        let swapped = self.framebuffer.swap_if_pending() | self.bitplanes.swap_if_pending();
+        if swapped {
+            self.update_native();
+        }
+    }
+
+    /// Transform the front buffers to the panel's native orientation.
+    fn update_native(&mut self) {
+        let orientation = self.config.orientation();
+        self.native = self.framebuffer.front().oriented(orientation);
+        self.native_bitplanes = self.bitplanes.front().oriented(orientation);
+    }
+
+    /// Start shifting out data to the display, without showing it yet.
+    fn queue_display_data(&mut self, words: &[u8]) -> Result<(), Error> {
+        self.spi.start_write(words).map_err(Error::spi)
+    }
+
+    /// Show the data that was last queued, enabling the outputs.
+    fn latch_display_data(&mut self) -> Result<(), Error> {
+        self.pin_oe.set_low().map_err(Error::pin)?;
+        self.pin_latch.set_low().map_err(Error::pin)?;
+
+        self.spi.wait().map_err(Error::spi)?;
+        self.delay.delay_us(2);
+
+        self.pin_latch.set_high().map_err(Error::pin)?;
+        self.delay.delay_us(self.config.latch_delay());
+        self.pin_latch.set_low().map_err(Error::pin)
+    }
+
+    // MARK: - Inputs
+
+    /// Handle button press events. This takes care of debouncing inputs and returns an event for button presses and releases.
+    ///
+    /// The handler function passed in to the function will be called for each button event.
+    ///
+    /// The [`scan`] method needs to be called at regular intervals for button presses to update.
+    ///
+    /// [`scan`]: #method.scan
+    pub fn handle_input_events<F>(&mut self, mut handler: F)
+    where
+        F: FnMut(ButtonEvent),
+    {
+        for y in 0..12_usize {
+            for x in 0..12_usize {
+                let cur = self.button_presses[y][x];
+
+                if cur == self.prev_read[y][x] {
+                    self.stable_count[y][x] = self.stable_count[y][x].saturating_add(1);
+                } else {
+                    self.stable_count[y][x] = 0;
+                    self.prev_read[y][x] = cur;
+                }
+
+                if self.stable_count[y][x] >= self.config.debounce_reads()
+                    && cur != self.stable_state[y][x]
+                {
+                    self.stable_state[y][x] = cur;
+
+                    // report the button in the same coordinates used for drawing
+                    let (x, y) = self.config.orientation().to_oriented((x, y));
+                    if cur {
+                        handler(ButtonEvent::Pressed(x as u8, y as u8));
+                    } else {
+                        handler(ButtonEvent::Released(x as u8, y as u8));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle button press events. This takes care of debouncing inputs and returns an event for button presses and releases.
+    ///
+    /// This uses sends events from [`handle_input_events`] but instead sends events through to a channel, rather than a handler function.
+    ///
+    /// The [`scan`] method needs to be called at regular intervals for button presses to update.
+    ///
+    /// [`handle_input_events`]: #method.handle_input_events
+    /// [`scan`]: #method.scan
+    #[cfg(feature = "embassy")]
+    pub fn handle_input_events_to_channel<const N: usize>(
+        &mut self,
+        ch: &embassy_sync::channel::Channel<
+            embassy_sync::blocking_mutex::raw::NoopRawMutex,
+            ButtonEvent,
+            N,
+        >,
+    ) {
+        self.handle_input_events(|e| {
+            let _ = ch.try_send(e);
+        });
+    }
+
+    /// Update the display while also scanning for button inputs.
+    ///
+    /// This draws each row in turn and checks for button presses on the same row before going to the next row.
+    ///
+    /// Any frame passed to [`DoubleBuffer::present`] is swapped to the front before drawing starts.
+    ///
+    /// This must be called at a regular interval to ensure the display is refreshed and button inputs are registered.
+    ///
+    /// Errors are ignored, so there is no sign that the SPI bus or a pin has failed: the rest of the scan is skipped and
+    /// the next call starts a new scan. Use [`try_scan`] to handle them.
+    ///
+    /// [`try_scan`]: #method.try_scan
+    pub fn scan(&mut self) {
+        let _ = self.try_scan();
+    }
+
+    /// Update the display while also scanning for button inputs, returning an error if the SPI bus or a pin fails.
+    ///
+    /// See [`scan`] for details.
+    ///
+    /// [`scan`]: #method.scan
+    pub fn try_scan(&mut self) -> Result<(), Error> {
+        // finish off a frame that was started with scan_tick
+        let step = core::mem::replace(&mut self.tick_step, TickStep::Channel);
+        if let Some(channel) = self.shown_channel.take() {
+            if step != TickStep::Channel {
+                // the channel is part way through being shown, so the input data has not been queued yet
+                self.queue_display_data(&self.first_input_data(channel))?;
+            }
+            self.scan_inputs(channel)?;
+        }
+
+        // this is a frame boundary, so show any newly presented frames
+        self.swap_buffers();
+
+        // drive each channel and scan its 12 columns
+        for channel in 0..CHANNELS {
+            // select this channel and show the normal frame first
+            self.display_channel(channel)?;
+            self.scan_inputs(channel)?;
+        }
+
+        self.next_channel = 0;
+        Ok(())
+    }
+
+    /// Advance the display and button scanning by a single step, without waiting for the display.
+    ///
+    /// This is intended to be called from a timer interrupt, so the display is refreshed in the background. Each call
+    /// does the work that is due, such as scanning the buttons for the channel that is shown and showing the next
+    /// channel, then returns straight away, leaving the display lit. The next call is due after
+    /// [`next_tick_delay`] microseconds.
+    ///
+    /// At full brightness in [`ScanMode::Standard`] there is one call for each channel, every [`channel_on_time`]
+    /// microseconds. Dimming the display adds a call to disable the outputs part way through, and
+    /// [`ScanMode::BinaryCodeModulation`] adds a call for each bitplane.
+    ///
+    /// Returns `true` when a full frame has been completed, at which point [`handle_input_events`] should be called.
+    ///
+    /// Errors are ignored, so there is no sign that the SPI bus or a pin has failed: the channel is retried on the next
+    /// call. Use [`try_scan_tick`] to handle them.
+    ///
+    /// [`channel_on_time`]: ArcadeCoderConfig::channel_on_time
+    /// [`handle_input_events`]: #method.handle_input_events
+    /// [`next_tick_delay`]: #method.next_tick_delay
+    /// [`try_scan_tick`]: #method.try_scan_tick
+    pub fn scan_tick(&mut self) -> bool {
+        self.try_scan_tick().unwrap_or(false)
+    }
+
+    /// Advance the display and button scanning by a single step, returning an error if the SPI bus or a pin fails.
+    ///
+    /// See [`scan_tick`] for details.
+    ///
+    /// [`scan_tick`]: #method.scan_tick
+    pub fn try_scan_tick(&mut self) -> Result<bool, Error> {
+        let result = self.advance_tick();
+
+        if result.is_err() {
+            // show the channel again from the start, as its data may not have been queued
+            if let Some(channel) = self.shown_channel.take() {
+                self.next_channel = channel;
+            }
+            self.tick_step = TickStep::Channel;
+            self.tick_delay = self.config.channel_on_time();
+        }
+
+        result
+    }
+
+    /// Get the time to wait before the next call to [`scan_tick`], in microseconds.
+    ///
+    /// [`scan_tick`]: #method.scan_tick
+    pub fn next_tick_delay(&self) -> u32 {
+        self.tick_delay
+    }
+
+    /// Run the steps of [`scan_tick`] until one needs to wait.
+    ///
+    /// [`scan_tick`]: #method.scan_tick
+    fn advance_tick(&mut self) -> Result<bool, Error> {
+        let mut frame_complete = false;
+
+        loop {
+            let wait = match self.tick_step {
+                TickStep::Channel => {
+                    if let Some(channel) = self.shown_channel.take() {
+                        self.scan_inputs(channel)?;
+                        frame_complete = channel == CHANNELS - 1;
+                    }
+
+                    let channel = self.next_channel;
+                    if channel == 0 {
+                        // this is a frame boundary, so show any newly presented frames
+                        self.swap_buffers();
+                    }
+
+                    // queue the first row so it is shifted out while the channel is selected
+                    let first = self.channel_row(channel, 0);
+                    self.queue_display_data(&first)?;
+                    self.set_channel(Some(channel))?;
+
+                    self.shown_channel = Some(channel);
+                    self.next_channel = (channel + 1) % CHANNELS;
+                    self.tick_row(channel, 0)?
+                }
+                TickStep::Row { channel, row } => self.tick_row(channel, row)?,
+                TickStep::Blank { channel, row } => {
+                    // oe is active low
+                    self.pin_oe.set_high().map_err(Error::pin)?;
+
+                    let (on_time, lit_time) = self.row_times(row);
+                    self.tick_step = self.step_after(channel, row);
+                    on_time - lit_time
+                }
+            };
+
+            // carry on straight away if there is nothing to wait for, unless a new channel is next
+            if wait > 0 || self.tick_step == TickStep::Channel {
+                self.tick_delay = wait.max(1);
+                return Ok(frame_complete);
+            }
+        }
+    }
+
+    /// Show a row of a channel for [`scan_tick`], returning the time to leave it lit for.
+    ///
+    /// [`scan_tick`]: #method.scan_tick
+    fn tick_row(&mut self, channel: usize, row: usize) -> Result<u32, Error> {
+        let (on_time, lit_time) = self.show_row(channel, row)?;
+
+        if lit_time < on_time {
+            // dim the display on the next step
+            self.tick_step = TickStep::Blank { channel, row };
+            Ok(lit_time)
+        } else {
+            self.tick_step = self.step_after(channel, row);
+            Ok(on_time)
+        }
+    }
+
+    /// Get the step after a row of a channel has been shown.
+    fn step_after(&self, channel: usize, row: usize) -> TickStep {
+        if row + 1 < self.display_rows() {
+            TickStep::Row {
+                channel,
+                row: row + 1,
+            }
+        } else {
+            TickStep::Channel
+        }
+    }
+
+    /// Get the data to queue for the start of the input scan.
+    fn first_input_data(&self, channel: usize) -> Row {
+        if self.config.reduce_ghosting() {
+            [0xff; ROW_BYTES]
+        } else {
+            self.input_test(channel, 0).0
+        }
+    }
+
+    /// Get the number of input tests to run for each channel.
+    fn input_tests(&self) -> usize {
+        match self.input_scan_mode {
+            InputScanMode::AllRows => TEST_PATTERNS / CHANNELS,
+            InputScanMode::PerChannel => TEST_PATTERNS,
+        }
+    }
+
+    /// Get the test pattern for one of a channel's input tests and the button it tests.
+    ///
+    /// When reading all input rows, the button is the one on the first input row.
+    fn input_test(&self, channel: usize, index: usize) -> (Row, Coordinates) {
+        match self.input_scan_mode {
+            // the test patterns only depend on the column and half, so are the same for every channel
+            InputScanMode::AllRows => test_pattern(0, channel * self.input_tests() + index),
+            InputScanMode::PerChannel => test_pattern(channel, index),
+        }
+    }
+
+    /// Scan the buttons for a channel. The data from [`first_input_data`] must already be queued.
+    ///
+    /// [`first_input_data`]: #method.first_input_data
+    fn scan_inputs(&mut self, channel: usize) -> Result<(), Error> {
+        if self.config.reduce_ghosting() {
+            // show the blank frame, then queue the first test pattern
+            self.latch_display_data()?;
+            self.queue_display_data(&self.input_test(channel, 0).0)?;
+            self.delay.delay_us(self.config.latch_delay());
+        }
+
+        // select the input channel while the first test pattern is shifted out
+        self.set_channel(None)?;
+
+        // scan columns for button presses
+        let tests = self.input_tests();
+        for i in 0..tests {
+            // show the test pattern
+            self.latch_display_data()?;
+
+            // queue the next pattern while the inputs settle
+            if i + 1 < tests {
+                self.queue_display_data(&self.input_test(channel, i + 1).0)?;
+            } else if self.config.reduce_ghosting() {
+                self.queue_display_data(&[0xff; ROW_BYTES])?;
+            }
+            self.delay.delay_us(self.config.latch_delay());
+
+            // read the input lines and store the state for this pass
+            let (x, y) = self.input_test(channel, i).1;
+            match self.input_scan_mode {
+                InputScanMode::AllRows => {
+                    for row in 0..CHANNELS {
+                        self.button_presses[y + row][x] =
+                            self.rows[row].is_high().map_err(Error::pin)?;
+                    }
+                }
+                InputScanMode::PerChannel => {
+                    self.button_presses[y][x] = self.rows[channel].is_high().map_err(Error::pin)?;
+                }
+            }
+        }
+
+        if self.config.reduce_ghosting() {
+            self.latch_display_data()?;
+            self.delay.delay_us(self.config.latch_delay());
+        }
+
+        Ok(())
+    }
+}
+
+/// Number of test patterns needed to scan the buttons on a channel, one for each column of both physical rows.
+const TEST_PATTERNS: usize = 12 * 2;
+
+/// Get a button test pattern for a channel and the button it tests.
+///
+/// The patterns go through each column in turn, testing the channel's top physical row then the bottom.
+fn test_pattern(channel: usize, index: usize) -> (Row, Coordinates) {
+    let pos = (index / 2, channel + (index % 2) * CHANNELS);
+
+    // get indexes corresponding to the column for the bits to be changed
+    let (byte_idx, bit_idx) = get_display_indexes(pos);
+
+    // for the input testing buffer, set the red bit to high
+    let mut buf = [0x00; ROW_BYTES];
+    buf[byte_idx + 1] |= 1 << bit_idx;
+
+    (buf, pos)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+    use embedded_hal_mock::eh1::digital::{Mock as PinMock, State, Transaction as PinTransaction};
+    use embedded_hal_mock::eh1::spi::{Mock as SpiMock, Transaction as SpiTransaction};
+
+    use super::*;
+    use crate::config::ConfigError;
+    use crate::orientation::{Orientation, Rotation};
+    use crate::RED;
+
+    pub(crate) type TestArcadeCoder = ArcadeCoder<SpiMock<u8>, PinMock, PinMock, NoopDelay>;
+
+    /// Multiplexer addresses of each display channel on the original board.
+    const CHANNEL_ADDRESSES: [u8; CHANNELS] = [0b010, 0b011, 0b101, 0b100, 0b001, 0b110];
+
+    /// Multiplexer address of the inputs on the original board.
+    const INPUT_ADDRESS: u8 = 0b000;
+
+    /// The expected SPI traffic and pin states, in order for each pin.
+    #[derive(Default)]
+    pub(crate) struct Expected {
+        spi: Vec<SpiTransaction<u8>>,
+        address: [Vec<PinTransaction>; 3],
+        oe: Vec<PinTransaction>,
+        latch: Vec<PinTransaction>,
+        rows: [Vec<PinTransaction>; CHANNELS],
+    }
+
+    impl Expected {
+        /// Start with the pin states set by `from_hal`.
+        pub(crate) fn new() -> Self {
+            let mut expected = Self::default();
+            expected.select(0b000);
+            expected.oe.push(PinTransaction::set(State::High));
+            expected.latch.push(PinTransaction::set(State::Low));
+            expected
+        }
+
+        fn select(&mut self, address: u8) {
+            for (bit, pin) in self.address.iter_mut().enumerate() {
+                let state = if address & (1 << bit) != 0 {
+                    State::High
+                } else {
+                    State::Low
+                };
+                pin.push(PinTransaction::set(state));
+            }
+        }
+
+        fn write(&mut self, row: Row) {
+            self.spi.push(SpiTransaction::write_vec(row.to_vec()));
+        }
+
+        fn latch(&mut self) {
+            self.oe.push(PinTransaction::set(State::Low));
+            self.latch.push(PinTransaction::set(State::Low));
+            self.spi.push(SpiTransaction::flush());
+            self.latch.push(PinTransaction::set(State::High));
+            self.latch.push(PinTransaction::set(State::Low));
+        }
+
+        fn read(&mut self, row: usize, pressed: bool) {
+            let state = if pressed { State::High } else { State::Low };
+            self.rows[row].push(PinTransaction::get(state));
+        }
+
+        /// Disable the outputs.
+        fn blank(&mut self) {
+            self.oe.push(PinTransaction::set(State::High));
+        }
+
+        /// Show a channel, with its first test pattern queued while it is lit.
+        pub(crate) fn show_channel(&mut self, channel: usize, data: Row) {
+            self.write(data);
+            self.select(CHANNEL_ADDRESSES[channel]);
+            self.latch();
+            self.write(test_pattern(channel, 0).0);
+        }
+
+        /// Test each button on a channel's input row, with one of the tests reading a press.
+        pub(crate) fn scan_channel(&mut self, channel: usize, pressed: Option<usize>) {
+            self.select(INPUT_ADDRESS);
+            for i in 0..TEST_PATTERNS {
+                self.latch();
+                if i + 1 < TEST_PATTERNS {
+                    self.write(test_pattern(channel, i + 1).0);
+                }
+                self.read(channel, pressed == Some(i));
+            }
+        }
+
+        /// Create a driver with mocks expecting these transactions, and clones of the mocks to check afterwards.
+        pub(crate) fn build(self) -> (TestArcadeCoder, Mocks) {
+            let mocks = Mocks {
+                spi: SpiMock::new(&self.spi),
+                address: self.address.map(|t| PinMock::new(&t)),
+                oe: PinMock::new(&self.oe),
+                latch: PinMock::new(&self.latch),
+                rows: self.rows.map(|t| PinMock::new(&t)),
+            };
+
+            let [a0, a1, a2] = mocks.address.clone();
+            let ac = ArcadeCoder::from_hal(
+                mocks.spi.clone(),
+                a0,
+                a1,
+                a2,
+                mocks.oe.clone(),
+                mocks.latch.clone(),
+                mocks.rows.clone(),
+                NoopDelay::new(),
+            )
+            .unwrap();
+            (ac, mocks)
+        }
+    }
+
+    pub(crate) struct Mocks {
+        spi: SpiMock<u8>,
+        address: [PinMock; 3],
+        oe: PinMock,
+        latch: PinMock,
+        rows: [PinMock; CHANNELS],
+    }
+
+    impl Mocks {
+        /// Check every expected transaction happened.
+        pub(crate) fn done(mut self) {
+            self.spi.done();
+            self.address.iter_mut().for_each(PinMock::done);
+            self.oe.done();
+            self.latch.done();
+            self.rows.iter_mut().for_each(PinMock::done);
+        }
+    }
+
+    /// Get a row with only the red bit for a column cleared, which is how a red pixel is drawn.
+    pub(crate) fn red_pixel_row(byte_idx: usize, bit_idx: usize) -> Row {
+        let mut row = [0xff; ROW_BYTES];
+        row[byte_idx + 1] &= !(1 << bit_idx);
+        row
+    }
+
+    /// Get a test pattern with only the red bit for a column set.
+    fn test_row(byte_idx: usize, bit_idx: usize) -> Row {
+        let mut row = [0x00; ROW_BYTES];
+        row[byte_idx + 1] = 1 << bit_idx;
+        row
+    }
+
+    #[test]
+    fn test_patterns_drive_one_column() {
+        // the first column of the top physical row, then the bottom row
+        assert_eq!(test_pattern(0, 0), (test_row(3, 4), (0, 0)));
+        assert_eq!(test_pattern(0, 1), (test_row(3, 0), (0, 6)));
+        assert_eq!(test_pattern(2, 10), (test_row(0, 1), (5, 2)));
+        assert_eq!(test_pattern(5, 23), (test_row(6, 7), (11, 11)));
+    }
+
+    #[test]
+    fn scan_writes_each_channel_then_scans_its_buttons() {
+        // a red pixel in the top-left, which is on channel 0
+        let mut expected = Expected::new();
+        expected.show_channel(0, red_pixel_row(3, 4));
+        expected.blank();
+        expected.scan_channel(0, None);
+        for channel in 1..CHANNELS {
+            expected.show_channel(channel, [0xff; ROW_BYTES]);
+            expected.blank();
+            expected.scan_channel(channel, None);
+        }
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+        ac.framebuffer.back_mut().set_pixel((0, 0), RED);
+        ac.framebuffer.present();
+        ac.try_scan().unwrap();
+
+        mocks.done();
+        assert_eq!(ac.button_presses, [[false; 12]; 12]);
+    }
+
+    #[test]
+    fn per_channel_scan_maps_pressed_button() {
+        // button (5, 8) is tested on channel 2, by the pattern for column 5 of the bottom physical row
+        let mut expected = Expected::new();
+        for channel in 0..CHANNELS {
+            expected.show_channel(channel, [0xff; ROW_BYTES]);
+            expected.blank();
+            expected.scan_channel(channel, (channel == 2).then_some(11));
+        }
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+        ac.try_scan().unwrap();
+        mocks.done();
+
+        let mut pressed = [[false; 12]; 12];
+        pressed[8][5] = true;
+        assert_eq!(ac.button_presses, pressed);
+    }
+
+    #[test]
+    fn scan_tick_leaves_channel_lit_at_full_brightness() {
+        let mut expected = Expected::new();
+        expected.show_channel(0, [0xff; ROW_BYTES]);
+        expected.scan_channel(0, None);
+        expected.show_channel(1, [0xff; ROW_BYTES]);
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+
+        // one tick for each channel, without disabling the outputs
+        assert!(!ac.try_scan_tick().unwrap());
+        assert_eq!(ac.next_tick_delay(), 1388);
+        assert!(!ac.try_scan_tick().unwrap());
+        assert_eq!(ac.next_tick_delay(), 1388);
+
+        mocks.done();
+    }
+
+    #[test]
+    fn scan_tick_dims_on_the_next_tick() {
+        let mut expected = Expected::new();
+        expected.show_channel(0, [0xff; ROW_BYTES]);
+        expected.blank();
+        expected.scan_channel(0, None);
+        expected.show_channel(1, [0xff; ROW_BYTES]);
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+        ac.brightness = 128;
+
+        // the outputs are disabled by their own tick, instead of waiting for the rest of the on time
+        ac.try_scan_tick().unwrap();
+        assert_eq!(ac.next_tick_delay(), 696);
+        ac.try_scan_tick().unwrap();
+        assert_eq!(ac.next_tick_delay(), 1388 - 696);
+        ac.try_scan_tick().unwrap();
+        assert_eq!(ac.next_tick_delay(), 696);
+
+        mocks.done();
+    }
+
+    #[test]
+    fn scan_tick_shows_each_bitplane_on_its_own_tick() {
+        let mut expected = Expected::new();
+        expected.write([0xff; ROW_BYTES]);
+        expected.select(CHANNEL_ADDRESSES[0]);
+        for _ in 1..BITPLANES {
+            expected.latch();
+            expected.write([0xff; ROW_BYTES]);
+        }
+        expected.latch();
+        expected.write(test_pattern(0, 0).0);
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+        ac.scan_mode = ScanMode::BinaryCodeModulation;
+
+        // each bitplane is shown for a time proportional to its weight
+        let delays: Vec<u32> = (0..BITPLANES)
+            .map(|_| {
+                ac.try_scan_tick().unwrap();
+                ac.next_tick_delay()
+            })
+            .collect();
+        assert_eq!(delays, [92, 185, 370, 740]);
+
+        mocks.done();
+    }
+
+    #[test]
+    fn scan_tick_completes_frame_after_last_channel() {
+        let mut expected = Expected::new();
+        for channel in 0..CHANNELS {
+            expected.show_channel(channel, [0xff; ROW_BYTES]);
+            expected.scan_channel(channel, None);
+        }
+        expected.show_channel(0, [0xff; ROW_BYTES]);
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+
+        let frames: Vec<bool> = (0..=CHANNELS)
+            .map(|_| ac.try_scan_tick().unwrap())
+            .collect();
+        assert_eq!(frames, [false, false, false, false, false, false, true]);
+
+        mocks.done();
+    }
+
+    #[test]
+    fn scan_finishes_a_channel_shown_by_scan_tick() {
+        // a dimmed channel is interrupted before its outputs were disabled, so the input data is queued again
+        let mut expected = Expected::new();
+        expected.show_channel(0, [0xff; ROW_BYTES]);
+        expected.write(test_pattern(0, 0).0);
+        expected.scan_channel(0, None);
+        for channel in 0..CHANNELS {
+            expected.show_channel(channel, [0xff; ROW_BYTES]);
+            expected.blank();
+            expected.scan_channel(channel, None);
+        }
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+        ac.brightness = 128;
+        ac.try_scan_tick().unwrap();
+        ac.try_scan().unwrap();
+
+        mocks.done();
+    }
+
+    #[test]
+    fn all_rows_scan_reads_every_input_row() {
+        // each channel sends 4 of the 24 test patterns, and every input row is read for each
+        let mut expected = Expected::new();
+        for (channel, address) in CHANNEL_ADDRESSES.into_iter().enumerate() {
+            let pattern = |i: usize| test_pattern(0, channel * 4 + i);
+
+            expected.write([0xff; ROW_BYTES]);
+            expected.select(address);
+            expected.latch();
+            expected.write(pattern(0).0);
+            expected.oe.push(PinTransaction::set(State::High));
+
+            expected.select(INPUT_ADDRESS);
+            for i in 0..4 {
+                expected.latch();
+                if i + 1 < 4 {
+                    expected.write(pattern(i + 1).0);
+                }
+                // button (5, 8) is on input row 2 with the pattern for column 5 of the bottom physical row
+                for row in 0..CHANNELS {
+                    expected.read(row, pattern(i).1 == (5, 6) && row == 2);
+                }
+            }
+        }
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::AllRows;
+        ac.try_scan().unwrap();
+        mocks.done();
+
+        let mut pressed = [[false; 12]; 12];
+        pressed[8][5] = true;
+        assert_eq!(ac.button_presses, pressed);
+    }
+
+    /// Set whether button (3, 4) is pressed for a number of scans, collecting the events.
+    fn read_button(ac: &mut TestArcadeCoder, pressed: bool, scans: usize) -> Vec<ButtonEvent> {
+        let mut events = Vec::new();
+        for _ in 0..scans {
+            ac.button_presses[4][3] = pressed;
+            ac.handle_input_events(|e| events.push(e));
+        }
+        events
+    }
+
+    #[test]
+    fn debounces_button_events() {
+        let (mut ac, mocks) = Expected::new().build();
+
+        // the state has to be read the same way for more than `debounce_reads` scans
+        assert_eq!(read_button(&mut ac, true, 5), []);
+        assert_eq!(read_button(&mut ac, false, 3), []);
+        assert_eq!(read_button(&mut ac, true, 5), []);
+        assert_eq!(read_button(&mut ac, false, 5), []);
+
+        assert_eq!(read_button(&mut ac, true, 6), [ButtonEvent::Pressed(3, 4)]);
+        assert_eq!(read_button(&mut ac, true, 10), []);
+        assert_eq!(
+            read_button(&mut ac, false, 6),
+            [ButtonEvent::Released(3, 4)]
+        );
+
+        mocks.done();
+    }
+
+    #[test]
+    fn button_events_use_the_display_orientation() {
+        let (mut ac, mocks) = Expected::new().build();
+        let config = ArcadeCoderConfig::arcade_coder()
+            .with_debounce_reads(1)
+            .with_orientation(Orientation::new(Rotation::Deg90));
+        ac.apply_config(&config).unwrap();
+
+        assert_eq!(read_button(&mut ac, true, 2), [ButtonEvent::Pressed(4, 8)]);
+
+        mocks.done();
+    }
+
+    #[test]
+    fn invalid_config_is_not_applied() {
+        let (mut ac, mocks) = Expected::new().build();
+        let config = ArcadeCoderConfig::arcade_coder().with_debounce_reads(1);
+        ac.apply_config(&config).unwrap();
+
+        let invalid = [
+            (config.with_spi_frequency(0), ConfigError::SpiFrequency),
+            (config.with_channel_on_time(0), ConfigError::ChannelOnTime),
+            (config.with_debounce_reads(0), ConfigError::DebounceReads),
+            (
+                config.with_channel_map([0, 1, 2, 3, 4, 8]),
+                ConfigError::InvalidAddress(8),
+            ),
+            (
+                config.with_input_channel(0b011),
+                ConfigError::DuplicateAddress(0b011),
+            ),
+        ];
+        for (invalid, error) in invalid {
+            // an invalid orientation change would also be kept back
+            let invalid = invalid.with_orientation(Orientation::new(Rotation::Deg180));
+            assert_eq!(ac.apply_config(&invalid), Err(Error::Config(error)));
+            assert_eq!(ac.config(), &config);
+        }
+
+        // the previous settings are still used
+        assert_eq!(read_button(&mut ac, true, 2), [ButtonEvent::Pressed(3, 4)]);
+
+        mocks.done();
+    }
+}
@@ -0,0 +1,196 @@
+//! Support for the [`embedded-graphics`](https://docs.rs/embedded-graphics) ecosystem
+//!
+//! With the `embedded-graphics` feature enabled, [`Framebuffer`] implements [`DrawTarget`] so any embedded-graphics
+//! drawable (primitives, text, images) can be rendered on the display using the [`Rgb111`] color type.
+//!
+//! ## Example
+//! ```
+//! use arcadecoder_hw::{embedded_graphics::Rgb111, framebuffer::Framebuffer};
+//! use embedded_graphics::{
+//!     prelude::*,
+//!     primitives::{Circle, PrimitiveStyle},
+//! };
+//!
+//! let mut fb = Framebuffer::new();
+//! Circle::new(Point::new(1, 1), 10)
+//!     .into_styled(PrimitiveStyle::with_stroke(Rgb111::RED, 1))
+//!     .draw(&mut fb)
+//!     .unwrap();
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Size},
+    pixelcolor::{PixelColor, Rgb888, RgbColor},
+    Pixel,
+};
+
+use crate::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::Color;
+
+/// 3-bit color for use with embedded-graphics, with one bit for each of red, green and blue.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Rgb111(pub Color);
+
+impl PixelColor for Rgb111 {
+    type Raw = ();
+}
+
+impl RgbColor for Rgb111 {
+    fn r(&self) -> u8 {
+        u8::from(self.0.red())
+    }
+
+    fn g(&self) -> u8 {
+        u8::from(self.0.green())
+    }
+
+    fn b(&self) -> u8 {
+        u8::from(self.0.blue())
+    }
+
+    const MAX_R: u8 = 1;
+    const MAX_G: u8 = 1;
+    const MAX_B: u8 = 1;
+
+    const BLACK: Self = Self(Color::Black);
+    const RED: Self = Self(Color::Red);
+    const GREEN: Self = Self(Color::Green);
+    const BLUE: Self = Self(Color::Blue);
+    const YELLOW: Self = Self(Color::Yellow);
+    const MAGENTA: Self = Self(Color::Magenta);
+    const CYAN: Self = Self(Color::Cyan);
+    const WHITE: Self = Self(Color::White);
+}
+
+impl From<Color> for Rgb111 {
+    fn from(color: Color) -> Self {
+        Self(color)
+    }
+}
+
+impl From<Rgb111> for Color {
+    fn from(color: Rgb111) -> Self {
+        color.0
+    }
+}
+
+impl From<Rgb888> for Rgb111 {
+    /// Quantise a 24-bit color by turning on each channel that is at least half brightness.
+    fn from(color: Rgb888) -> Self {
+        Self(Color::from_rgb888(color.r(), color.g(), color.b()))
+    }
+}
+
+impl From<Rgb111> for Rgb888 {
+    fn from(color: Rgb111) -> Self {
+        Rgb888::new(color.r() * 0xff, color.g() * 0xff, color.b() * 0xff)
+    }
+}
+
+impl OriginDimensions for Framebuffer {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for Framebuffer {
+    type Color = Rgb111;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            // pixels off the display are skipped by set_pixel
+            self.set_pixel((point.x, point.y), color.0);
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.fill(color.0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::{
+        prelude::*,
+        primitives::{PrimitiveStyle, Rectangle},
+    };
+
+    #[test]
+    fn rgb888_channel_thresholds() {
+        assert_eq!(Rgb111::from(Rgb888::new(0x7f, 0x7f, 0x7f)), Rgb111::BLACK);
+        assert_eq!(Rgb111::from(Rgb888::new(0x80, 0x80, 0x80)), Rgb111::WHITE);
+        assert_eq!(Rgb111::from(Rgb888::new(0x80, 0x7f, 0x00)), Rgb111::RED);
+        assert_eq!(Rgb111::from(Rgb888::new(0x00, 0x80, 0x7f)), Rgb111::GREEN);
+        assert_eq!(Rgb111::from(Rgb888::new(0x7f, 0x00, 0x80)), Rgb111::BLUE);
+        assert_eq!(Rgb111::from(Rgb888::new(0xff, 0xff, 0x10)), Rgb111::YELLOW);
+
+        for color in Color::ALL {
+            let rgb = Rgb888::from(Rgb111(color));
+            assert_eq!((rgb.r(), rgb.g(), rgb.b()), color.to_rgb888());
+            assert_eq!(Rgb111::from(rgb), Rgb111(color));
+        }
+    }
+
+    #[test]
+    fn draw_iter_sets_pixels() {
+        let mut fb = Framebuffer::new();
+        fb.draw_iter([
+            Pixel(Point::new(0, 0), Rgb111::RED),
+            Pixel(Point::new(11, 11), Rgb111::CYAN),
+        ])
+        .unwrap();
+
+        let mut expected = Framebuffer::new();
+        expected.set_pixel((0, 0), Color::Red);
+        expected.set_pixel((11, 11), Color::Cyan);
+        assert_eq!(fb, expected);
+    }
+
+    #[test]
+    fn draw_iter_drops_pixels_off_the_display() {
+        let mut fb = Framebuffer::new();
+        fb.draw_iter([
+            Pixel(Point::new(-1, 0), Rgb111::WHITE),
+            Pixel(Point::new(0, -1), Rgb111::WHITE),
+            Pixel(Point::new(12, 0), Rgb111::WHITE),
+            Pixel(Point::new(0, 12), Rgb111::WHITE),
+            Pixel(Point::new(i32::MIN, i32::MIN), Rgb111::WHITE),
+            Pixel(Point::new(i32::MAX, i32::MAX), Rgb111::WHITE),
+        ])
+        .unwrap();
+        assert_eq!(fb, Framebuffer::new());
+
+        // only the part of the rectangle on the display is drawn
+        Rectangle::new(Point::new(-4, -4), Size::new(6, 6))
+            .into_styled(PrimitiveStyle::with_fill(Rgb111::GREEN))
+            .draw(&mut fb)
+            .unwrap();
+        for ((x, y), color) in fb.pixels() {
+            let expected = if x < 2 && y < 2 {
+                Color::Green
+            } else {
+                Color::Black
+            };
+            assert_eq!(color, expected, "({x}, {y})");
+        }
+    }
+
+    #[test]
+    fn clear_fills_display() {
+        let mut fb = Framebuffer::new();
+        DrawTarget::clear(&mut fb, Rgb111::MAGENTA).unwrap();
+        assert!(fb.pixels().all(|(_, color)| color == Color::Magenta));
+        assert_eq!(fb.size(), Size::new(12, 12));
+    }
+}
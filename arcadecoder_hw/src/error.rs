@@ -0,0 +1,66 @@
+//! Errors returned by the driver
+//!
+//! Errors from the SPI bus and pins are converted to the [`embedded_hal`] error kinds, so [`Error`] is the same type
+//! whichever HAL is used. With the `log` or `defmt` feature enabled, errors are also reported when they happen.
+
+use core::fmt;
+
+use embedded_hal::{digital, spi};
+
+use crate::config::ConfigError;
+
+/// An error from the Arcade Coder driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The driver settings are invalid.
+    Config(ConfigError),
+
+    /// The SPI bus could not be configured.
+    SpiConfig,
+
+    /// The DMA buffer is too small to hold a channel of display data.
+    DmaBuffer,
+
+    /// Writing display data to the SPI bus failed.
+    Spi(spi::ErrorKind),
+
+    /// Setting an output pin or reading a button input failed.
+    Pin(digital::ErrorKind),
+}
+
+impl Error {
+    /// Convert an SPI bus error, reporting it if logging is enabled.
+    pub(crate) fn spi(error: impl spi::Error) -> Self {
+        Self::Spi(error.kind()).reported()
+    }
+
+    /// Convert a pin error, reporting it if logging is enabled.
+    pub(crate) fn pin(error: impl digital::Error) -> Self {
+        Self::Pin(error.kind()).reported()
+    }
+
+    /// Report the error using `log` or `defmt` if either feature is enabled.
+    pub(crate) fn reported(self) -> Self {
+        #[cfg(feature = "log")]
+        log::error!("arcade coder: {}", self);
+        #[cfg(feature = "defmt")]
+        defmt::error!("arcade coder: {}", self);
+
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(e) => write!(f, "invalid config: {e}"),
+            Self::SpiConfig => write!(f, "could not configure the spi bus"),
+            Self::DmaBuffer => write!(f, "dma buffer is too small for a channel of display data"),
+            Self::Spi(kind) => write!(f, "could not write display data: {kind}"),
+            Self::Pin(kind) => write!(f, "could not use pin: {kind}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
@@ -0,0 +1,487 @@
+//! Convenience constructor and helpers for the ESP32 on the Arcade Coder.
+
+#[cfg(feature = "refresh")]
+use core::cell::RefCell;
+
+use esp_hal::delay::Delay;
+use esp_hal::gpio::{Input, InputConfig, InputPin, Level, Output, OutputConfig, OutputPin, Pull};
+use esp_hal::peripherals::{
+    GPIO16, GPIO17, GPIO18, GPIO19, GPIO21, GPIO32, GPIO33, GPIO34, GPIO35, GPIO36, GPIO39, GPIO4,
+    GPIO5, SPI2,
+};
+use esp_hal::spi::master::Spi;
+use esp_hal::time::Rate;
+use esp_hal::Blocking;
+#[cfg(feature = "dma")]
+use esp_hal::{
+    dma::{DmaChannelFor, DmaTxBuf},
+    spi::master::{AnySpi, SpiDma, SpiDmaTransfer},
+};
+#[cfg(feature = "refresh")]
+use esp_hal::{
+    interrupt::InterruptHandler,
+    system::{AppCoreGuard, CpuControl, Stack},
+    time::Duration,
+    timer::PeriodicTimer,
+};
+
+use crate::config::ArcadeCoderConfig;
+use crate::framebuffer::CHANNELS;
+#[cfg(feature = "dma")]
+use crate::framebuffer::ROW_BYTES;
+#[cfg(feature = "refresh")]
+use crate::refresh::{Refresh, Shared};
+#[cfg(any(feature = "dma", feature = "refresh"))]
+use crate::spi::DisplaySpi;
+use crate::{ArcadeCoder, Error};
+
+/// The [`ArcadeCoder`] driver using the ESP32 peripherals.
+pub type Esp32ArcadeCoder<'a> = ArcadeCoder<Spi<'a, Blocking>, Output<'a>, Input<'a>, Delay>;
+
+/// The ESP32 peripherals used by the Arcade Coder, following the pin map of the original board.
+///
+/// Use [`arcade_coder_peripherals!`](crate::arcade_coder_peripherals) to take them from the
+/// [`Peripherals`](esp_hal::peripherals::Peripherals), leaving the rest available.
+pub struct ArcadeCoderPeripherals<'a> {
+    pub spi: SPI2<'a>,
+    pub a0: GPIO19<'a>,
+    pub a1: GPIO18<'a>,
+    pub a2: GPIO21<'a>,
+    pub oe: GPIO4<'a>,
+    pub latch: GPIO16<'a>,
+    pub data: GPIO5<'a>,
+    pub clock: GPIO17<'a>,
+    pub inputs_1_7: GPIO39<'a>,
+    pub inputs_2_8: GPIO36<'a>,
+    pub inputs_3_9: GPIO35<'a>,
+    pub inputs_4_10: GPIO34<'a>,
+    pub inputs_5_11: GPIO33<'a>,
+    pub inputs_6_12: GPIO32<'a>,
+}
+
+/// Take the [`ArcadeCoderPeripherals`] from the ESP32 peripherals, leaving the rest of the peripherals available.
+///
+/// **Example:**
+///
+/// ```
+/// let p = esp_hal::init(esp_hal::Config::default());
+///
+/// let mut ac = ArcadeCoder::from_peripherals(
+///     arcade_coder_peripherals!(p),
+///     &ArcadeCoderConfig::default(),
+/// )
+/// .unwrap();
+/// ```
+#[macro_export]
+macro_rules! arcade_coder_peripherals {
+    ($p:ident) => {
+        $crate::ArcadeCoderPeripherals {
+            spi: $p.SPI2,
+            a0: $p.GPIO19,
+            a1: $p.GPIO18,
+            a2: $p.GPIO21,
+            oe: $p.GPIO4,
+            latch: $p.GPIO16,
+            data: $p.GPIO5,
+            clock: $p.GPIO17,
+            inputs_1_7: $p.GPIO39,
+            inputs_2_8: $p.GPIO36,
+            inputs_3_9: $p.GPIO35,
+            inputs_4_10: $p.GPIO34,
+            inputs_5_11: $p.GPIO33,
+            inputs_6_12: $p.GPIO32,
+        }
+    };
+}
+
+impl<'a> ArcadeCoder<Spi<'a, Blocking>, Output<'a>, Input<'a>, Delay> {
+    /// Create a new instance of the Arcade Coder on the original board's pins, with the given settings.
+    ///
+    /// Returns an error if the settings are invalid or the SPI bus could not be configured.
+    pub fn from_peripherals(
+        p: ArcadeCoderPeripherals<'a>,
+        config: &ArcadeCoderConfig,
+    ) -> Result<Self, Error> {
+        Self::with_config(
+            p.spi,
+            p.a0,
+            p.a1,
+            p.a2,
+            p.oe,
+            p.latch,
+            p.data,
+            p.clock,
+            [
+                button_input(p.inputs_1_7),
+                button_input(p.inputs_2_8),
+                button_input(p.inputs_3_9),
+                button_input(p.inputs_4_10),
+                button_input(p.inputs_5_11),
+                button_input(p.inputs_6_12),
+            ],
+            config,
+        )
+    }
+
+    /// Create a new instance of the Arcade Coder with the settings for the original board.
+    ///
+    /// Returns an error if the SPI bus could not be configured.
+    ///
+    /// **Example:**
+    ///
+    /// ```
+    /// let p = esp_hal::init(esp_hal::Config::default());
+    ///
+    /// let mut ac = ArcadeCoder::new(
+    ///     p.SPI2, p.GPIO19, p.GPIO18, p.GPIO21, p.GPIO4, p.GPIO16, p.GPIO5, p.GPIO17, p.GPIO39,
+    ///     p.GPIO36, p.GPIO35, p.GPIO34, p.GPIO33, p.GPIO32,
+    /// )
+    /// .unwrap();
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        spi_bus: SPI2<'a>,
+        pin_a0: impl OutputPin + 'a,
+        pin_a1: impl OutputPin + 'a,
+        pin_a2: impl OutputPin + 'a,
+        pin_oe: impl OutputPin + 'a,
+        pin_latch: impl OutputPin + 'a,
+        pin_data: impl OutputPin + 'a,
+        pin_clock: impl OutputPin + 'a,
+        inputs_1_7: impl InputPin + 'a,
+        inputs_2_8: impl InputPin + 'a,
+        inputs_3_9: impl InputPin + 'a,
+        inputs_4_10: impl InputPin + 'a,
+        inputs_5_11: impl InputPin + 'a,
+        inputs_6_12: impl InputPin + 'a,
+    ) -> Result<Self, Error> {
+        Self::with_config(
+            spi_bus,
+            pin_a0,
+            pin_a1,
+            pin_a2,
+            pin_oe,
+            pin_latch,
+            pin_data,
+            pin_clock,
+            [
+                button_input(inputs_1_7),
+                button_input(inputs_2_8),
+                button_input(inputs_3_9),
+                button_input(inputs_4_10),
+                button_input(inputs_5_11),
+                button_input(inputs_6_12),
+            ],
+            &ArcadeCoderConfig::arcade_coder(),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn with_config(
+        spi_bus: SPI2<'a>,
+        pin_a0: impl OutputPin + 'a,
+        pin_a1: impl OutputPin + 'a,
+        pin_a2: impl OutputPin + 'a,
+        pin_oe: impl OutputPin + 'a,
+        pin_latch: impl OutputPin + 'a,
+        pin_data: impl OutputPin + 'a,
+        pin_clock: impl OutputPin + 'a,
+        inputs: [Input<'a>; CHANNELS],
+        config: &ArcadeCoderConfig,
+    ) -> Result<Self, Error> {
+        config.validate().map_err(|e| Error::Config(e).reported())?;
+
+        let output_cfg: OutputConfig = OutputConfig::default();
+
+        let spi = Spi::new(
+            spi_bus,
+            esp_hal::spi::master::Config::default()
+                .with_frequency(Rate::from_hz(config.spi_frequency()))
+                .with_mode(esp_hal::spi::Mode::_0)
+                .with_write_bit_order(esp_hal::spi::BitOrder::MsbFirst),
+        )
+        .map_err(|_| Error::SpiConfig.reported())?
+        .with_mosi(pin_data)
+        .with_sck(pin_clock);
+
+        let mut ac = Self::from_hal(
+            spi,
+            Output::new(pin_a0, Level::Low, output_cfg),
+            Output::new(pin_a1, Level::Low, output_cfg),
+            Output::new(pin_a2, Level::Low, output_cfg),
+            Output::new(pin_oe, Level::High, output_cfg),
+            Output::new(pin_latch, Level::Low, output_cfg),
+            inputs,
+            Delay::new(),
+        )?;
+        ac.apply_config(config)?;
+        Ok(ac)
+    }
+}
+
+/// Set up a button row input with a pull-up.
+fn button_input<'a>(pin: impl InputPin + 'a) -> Input<'a> {
+    Input::new(pin, InputConfig::default().with_pull(Pull::Up))
+}
+
+/// The [`ArcadeCoder`] driver using the ESP32 peripherals, with SPI writes done using DMA.
+#[cfg(feature = "dma")]
+pub type Esp32DmaArcadeCoder<'a> = ArcadeCoder<Esp32DmaSpi<'a>, Output<'a>, Input<'a>, Delay>;
+
+#[cfg(feature = "dma")]
+impl<'a> ArcadeCoder<Spi<'a, Blocking>, Output<'a>, Input<'a>, Delay> {
+    /// Switch to writing display data using DMA, so data is shifted out while the driver waits for the display and
+    /// inputs.
+    ///
+    /// Returns an error if the buffer cannot hold a channel of display data.
+    ///
+    /// **Example:**
+    ///
+    /// ```
+    /// let tx_buf = esp_hal::dma_tx_buffer!(32).unwrap();
+    /// let mut ac = ArcadeCoder::new(/* ... */)
+    ///     .unwrap()
+    ///     .with_dma(p.DMA_SPI2, tx_buf)
+    ///     .unwrap();
+    /// ```
+    pub fn with_dma(
+        self,
+        channel: impl DmaChannelFor<AnySpi<'a>>,
+        tx_buf: DmaTxBuf,
+    ) -> Result<Esp32DmaArcadeCoder<'a>, Error> {
+        check_dma_buffer(&tx_buf)?;
+        Ok(self.map_spi(|spi| Esp32DmaSpi {
+            state: DmaState::Idle(spi.with_dma(channel), tx_buf),
+        }))
+    }
+}
+
+/// An SPI bus that writes display data in the background using DMA.
+#[cfg(feature = "dma")]
+pub struct Esp32DmaSpi<'a> {
+    state: DmaState<'a>,
+}
+
+#[cfg(feature = "dma")]
+enum DmaState<'a> {
+    Idle(SpiDma<'a, Blocking>, DmaTxBuf),
+    Busy(SpiDmaTransfer<'a, Blocking, DmaTxBuf>),
+    // only seen if taking the state panics
+    Invalid,
+}
+
+#[cfg(feature = "dma")]
+impl<'a> Esp32DmaSpi<'a> {
+    /// Create a new DMA SPI bus.
+    ///
+    /// Returns an error if the buffer cannot hold a channel of display data.
+    pub fn new(spi: SpiDma<'a, Blocking>, tx_buf: DmaTxBuf) -> Result<Self, Error> {
+        check_dma_buffer(&tx_buf)?;
+        Ok(Self {
+            state: DmaState::Idle(spi, tx_buf),
+        })
+    }
+}
+
+/// Check a DMA buffer can hold a channel of display data.
+#[cfg(feature = "dma")]
+fn check_dma_buffer(tx_buf: &DmaTxBuf) -> Result<(), Error> {
+    if tx_buf.capacity() < ROW_BYTES {
+        return Err(Error::DmaBuffer.reported());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "dma")]
+impl DisplaySpi for Esp32DmaSpi<'_> {
+    type Error = esp_hal::spi::Error;
+
+    fn start_write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.wait()?;
+
+        let DmaState::Idle(spi, mut buf) = core::mem::replace(&mut self.state, DmaState::Invalid)
+        else {
+            unreachable!("spi should be idle after waiting");
+        };
+
+        // the buffer is checked when the bus is created, so this only fails for more than a channel of data
+        let Some(data) = buf.as_mut_slice().get_mut(..words.len()) else {
+            self.state = DmaState::Idle(spi, buf);
+            return Err(esp_hal::spi::Error::MaxDmaTransferSizeExceeded);
+        };
+        data.copy_from_slice(words);
+        buf.set_length(words.len());
+
+        match spi.write(words.len(), buf) {
+            Ok(transfer) => {
+                self.state = DmaState::Busy(transfer);
+                Ok(())
+            }
+            Err((e, spi, buf)) => {
+                self.state = DmaState::Idle(spi, buf);
+                Err(e)
+            }
+        }
+    }
+
+    fn wait(&mut self) -> Result<(), Self::Error> {
+        if let DmaState::Busy(_) = self.state {
+            let DmaState::Busy(transfer) = core::mem::replace(&mut self.state, DmaState::Invalid)
+            else {
+                unreachable!();
+            };
+            let (spi, buf) = transfer.wait();
+            self.state = DmaState::Idle(spi, buf);
+        }
+
+        Ok(())
+    }
+}
+
+/// Shared driver state for refreshing the display from the ESP32's APP (second) core.
+///
+/// See [`Refresh::start_app_core`].
+#[cfg(feature = "refresh")]
+pub type Esp32AppCoreRefresh<const N: usize, SPI = Spi<'static, Blocking>> =
+    Refresh<SPI, Output<'static>, Input<'static>, Delay, N>;
+
+#[cfg(feature = "refresh")]
+impl<const N: usize, SPI> Esp32AppCoreRefresh<N, SPI>
+where
+    SPI: DisplaySpi + Send,
+{
+    /// Start refreshing the display and scanning the buttons on the APP (second) core.
+    ///
+    /// This leaves the main core free for Wi-Fi, game logic and storage without causing the display to stutter. Draw
+    /// with [`present`](Refresh::present) and read button events with [`pop_event`](Refresh::pop_event) as usual.
+    ///
+    /// The second core is stopped when the returned guard is dropped, so it must be kept alive.
+    ///
+    /// **Example:**
+    ///
+    /// ```
+    /// use esp_hal::system::{CpuControl, Stack};
+    ///
+    /// static REFRESH: Esp32AppCoreRefresh<32> = Esp32AppCoreRefresh::new();
+    /// static mut APP_CORE_STACK: Stack<8192> = Stack::new();
+    ///
+    /// let mut cpu_control = CpuControl::new(p.CPU_CTRL);
+    /// let _guard = REFRESH
+    ///     .start_app_core(ac, &mut cpu_control, unsafe { &mut *core::ptr::addr_of_mut!(APP_CORE_STACK) })
+    ///     .unwrap();
+    /// ```
+    pub fn start_app_core<const SIZE: usize>(
+        &'static self,
+        ac: ArcadeCoder<SPI, Output<'static>, Input<'static>, Delay>,
+        cpu_control: &mut CpuControl<'_>,
+        stack: &'static mut Stack<SIZE>,
+    ) -> Result<AppCoreGuard<'static>, esp_hal::system::Error> {
+        self.install(ac);
+        cpu_control.start_app_core(stack, move || self.run(Delay::new()))
+    }
+}
+
+/// Background display refresh on the ESP32, driven by a timer interrupt.
+///
+/// The timer is restarted after each tick to fire when the next one is due, so the display is never waited for in the
+/// interrupt. This dereferences to [`Refresh`] for drawing and reading button events.
+///
+/// **Example:**
+///
+/// ```
+/// use esp_hal::{handler, timer::{timg::TimerGroup, PeriodicTimer}};
+///
+/// static REFRESH: Esp32Refresh<32> = Esp32Refresh::new();
+///
+/// #[handler]
+/// fn refresh_handler() {
+///     REFRESH.on_interrupt();
+/// }
+///
+/// let timg1 = TimerGroup::new(p.TIMG1);
+/// REFRESH
+///     .start(ac, PeriodicTimer::new(timg1.timer0), refresh_handler)
+///     .unwrap();
+///
+/// loop {
+///     while let Some(event) = REFRESH.pop_event() {
+///         // handle button events
+///     }
+/// }
+/// ```
+#[cfg(feature = "refresh")]
+pub struct Esp32Refresh<const N: usize, SPI = Spi<'static, Blocking>> {
+    refresh: Esp32AppCoreRefresh<N, SPI>,
+    timer: Shared<Option<PeriodicTimer<'static, Blocking>>>,
+}
+
+#[cfg(feature = "refresh")]
+impl<const N: usize, SPI> Default for Esp32Refresh<N, SPI> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "refresh")]
+impl<const N: usize, SPI> Esp32Refresh<N, SPI> {
+    /// Create a new refresh engine. This can be used to initialise a `static`.
+    pub const fn new() -> Self {
+        Self {
+            refresh: Refresh::new(),
+            timer: critical_section::Mutex::new(RefCell::new(None)),
+        }
+    }
+}
+
+#[cfg(feature = "refresh")]
+impl<const N: usize, SPI> Esp32Refresh<N, SPI>
+where
+    SPI: DisplaySpi,
+{
+    /// Start refreshing the display in the background.
+    ///
+    /// The timer fires after [`next_tick_delay`](Refresh::next_tick_delay) microseconds and `handler` must call
+    /// [`on_interrupt`](Self::on_interrupt).
+    pub fn start(
+        &self,
+        ac: ArcadeCoder<SPI, Output<'static>, Input<'static>, Delay>,
+        timer: PeriodicTimer<'static, Blocking>,
+        handler: InterruptHandler,
+    ) -> Result<(), esp_hal::timer::Error> {
+        let period = Duration::from_micros(ac.next_tick_delay().into());
+        self.refresh.install(ac);
+
+        // store the timer before starting it so the first interrupt can be cleared
+        critical_section::with(|cs| {
+            let mut slot = self.timer.borrow_ref_mut(cs);
+            let timer = slot.insert(timer);
+            timer.set_interrupt_handler(handler);
+            timer.listen();
+            timer.start(period)
+        })
+    }
+
+    /// Handle the timer interrupt, advancing the display by one step.
+    pub fn on_interrupt(&self) {
+        self.refresh.tick();
+
+        // restart the timer for when the next step is due
+        let period = Duration::from_micros(self.refresh.next_tick_delay().into());
+        critical_section::with(|cs| {
+            if let Some(timer) = self.timer.borrow_ref_mut(cs).as_mut() {
+                timer.clear_interrupt();
+                // if this fails, the timer keeps firing at the previous period
+                let _ = timer.start(period);
+            }
+        });
+    }
+}
+
+#[cfg(feature = "refresh")]
+impl<const N: usize, SPI> core::ops::Deref for Esp32Refresh<N, SPI> {
+    type Target = Esp32AppCoreRefresh<N, SPI>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.refresh
+    }
+}
@@ -1,61 +1,603 @@
-//! Simple fonts for displaying numbers
+//! Bitmap fonts for displaying text
 //!
-//! Fonts are made up of an array of booleans representing on and off pixels.
+//! A [`Font`] stores each glyph as packed bits, one bit per pixel in rows from the top-left, starting on a byte
+//! boundary. Glyphs can have different widths, and each has an advance which is the distance to the start of the next
+//! character. Characters are mapped to glyphs using ranges, so a font only needs to include the characters it uses.
+//! [`Font::with_letter_spacing`] changes the space between characters without changing the glyphs.
+//!
+//! The built-in fonts cover the digits, the letters `A` to `Z` and the printable ASCII symbols. Lowercase letters are
+//! drawn in uppercase when a font has no lowercase glyphs.
 
-pub type Font<'a> = &'a [&'a [bool]];
+/// A bitmap font.
+#[derive(Clone, Copy, Debug)]
+pub struct Font<'a> {
+    height: u8,
+    baseline: u8,
+    ranges: &'a [GlyphRange],
+    glyphs: &'a [Glyph],
+    bitmap: &'a [u8],
+    letter_spacing: i8,
+}
 
-pub const FONT_5X5_SIZE: (usize, usize) = (5, 5);
+/// A range of characters mapped to consecutive glyphs in a [`Font`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GlyphRange {
+    first: char,
+    last: char,
+    glyph: u16,
+}
 
-/// A basic 5x5 pixel font
-pub static FONT_5X5: Font = &[
-    // 0
-    &[
-        false, true, true, true, false, true, false, false, false, true, true, false, false, false,
-        true, true, false, false, false, true, false, true, true, true, false,
-    ],
-    // 1
-    &[
-        false, false, true, false, false, true, true, true, false, false, false, false, true,
-        false, false, false, false, true, false, false, true, true, true, true, true,
-    ],
-    // 2
-    &[
-        true, true, true, true, false, false, false, false, false, true, false, true, true, true,
-        false, true, false, false, false, false, true, true, true, true, true,
-    ],
-    // 3
-    &[
-        true, true, true, true, false, false, false, false, false, true, true, true, true, true,
-        false, false, false, false, false, true, true, true, true, true, false,
-    ],
-    // 4
-    &[
-        true, false, false, true, false, true, false, false, true, false, true, false, false, true,
-        false, true, true, true, true, true, false, false, false, true, false,
-    ],
-    // 5
-    &[
-        true, true, true, true, true, true, false, false, false, false, true, true, true, true,
-        false, false, false, false, false, true, true, true, true, true, false,
-    ],
-    // 6
+/// The position and metrics of a glyph in a [`Font`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Glyph {
+    offset: u16,
+    width: u8,
+    advance: u8,
+}
+
+/// A single glyph from a [`Font`], ready to be drawn.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphBitmap<'a> {
+    width: usize,
+    height: usize,
+    advance: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Font<'a> {
+    /// Create a new font.
+    ///
+    /// Every glyph is `height` pixels tall, and `baseline` is the number of rows from the top of the glyph to the
+    /// baseline. The `bitmap` holds the packed bits for all glyphs, found using the offset of each [`Glyph`].
+    pub const fn new(
+        height: u8,
+        baseline: u8,
+        ranges: &'a [GlyphRange],
+        glyphs: &'a [Glyph],
+        bitmap: &'a [u8],
+    ) -> Self {
+        Self {
+            height,
+            baseline,
+            ranges,
+            glyphs,
+            bitmap,
+            letter_spacing: 0,
+        }
+    }
+
+    /// Add extra pixels between characters, or remove them with a negative spacing.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::font::FONT_5X5;
+    ///
+    /// let wide = FONT_5X5.with_letter_spacing(1);
+    /// assert_eq!(wide.measure_text("12"), FONT_5X5.measure_text("12") + 1);
+    /// ```
+    pub const fn with_letter_spacing(mut self, spacing: i8) -> Self {
+        self.letter_spacing = spacing;
+        self
+    }
+
+    /// Height of the glyphs in pixels.
+    pub const fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    /// Number of rows from the top of the glyphs to the baseline.
+    pub const fn baseline(&self) -> usize {
+        self.baseline as usize
+    }
+
+    /// Extra pixels added between characters.
+    pub const fn letter_spacing(&self) -> i8 {
+        self.letter_spacing
+    }
+
+    /// Width of a string in pixels, from the left of the first character to the right of the last.
+    ///
+    /// Characters that are not in the font are skipped, like when drawing.
+    pub fn measure_text(&self, text: &str) -> usize {
+        measure_glyphs(text.chars().filter_map(|c| self.glyph(c)))
+    }
+
+    /// Get the glyph for a character, or `None` if the font does not have it.
+    ///
+    /// Lowercase letters fall back to the uppercase glyphs if the font has no lowercase glyph.
+    pub fn glyph(&self, character: char) -> Option<GlyphBitmap<'a>> {
+        self.lookup(character)
+            .or_else(|| self.lookup(character.to_ascii_uppercase()))
+    }
+
+    fn lookup(&self, character: char) -> Option<GlyphBitmap<'a>> {
+        let range = self
+            .ranges
+            .iter()
+            .find(|r| (r.first..=r.last).contains(&character))?;
+        let index = usize::from(range.glyph) + (character as usize - range.first as usize);
+        let glyph = self.glyphs.get(index)?;
+
+        Some(GlyphBitmap {
+            width: usize::from(glyph.width),
+            height: self.height(),
+            advance: usize::try_from(i16::from(glyph.advance) + i16::from(self.letter_spacing))
+                .unwrap_or_default(),
+            data: self
+                .bitmap
+                .get(usize::from(glyph.offset)..)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl GlyphRange {
+    /// Map the characters from `first` to `last` (inclusive) to the glyphs starting at index `glyph`.
+    pub const fn new(first: char, last: char, glyph: u16) -> Self {
+        Self { first, last, glyph }
+    }
+}
+
+impl Glyph {
+    /// Create a glyph whose bits start at byte `offset` in the font's bitmap.
+    pub const fn new(offset: u16, width: u8, advance: u8) -> Self {
+        Self {
+            offset,
+            width,
+            advance,
+        }
+    }
+}
+
+impl GlyphBitmap<'_> {
+    /// Width of the glyph in pixels.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the glyph in pixels.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Distance from the start of this glyph to the start of the next in pixels, including the font's letter spacing.
+    pub const fn advance(&self) -> usize {
+        self.advance
+    }
+
+    /// Whether a pixel of the glyph is on. Pixels outside the glyph are off.
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+
+        let bit = y * self.width + x;
+        self.data
+            .get(bit / 8)
+            .is_some_and(|byte| byte & (0x80 >> (bit % 8)) != 0)
+    }
+}
+
+/// Width of a run of glyphs drawn one after another, from the left of the first to the right of the last.
+pub(crate) fn measure_glyphs<'a>(glyphs: impl Iterator<Item = GlyphBitmap<'a>>) -> usize {
+    let mut pen = 0;
+    let mut width = 0;
+    for glyph in glyphs {
+        width = width.max(pen + glyph.width());
+        pen += glyph.advance();
+    }
+    width
+}
+
+/// A basic 5x5 pixel font. Digits are all the same width so numbers line up.
+pub static FONT_5X5: Font = Font::new(
+    5,
+    5,
+    &[GlyphRange::new(' ', '`', 0), GlyphRange::new('{', '~', 65)],
     &[
-        false, true, true, true, false, true, false, false, false, false, true, true, true, true,
-        false, true, false, false, false, true, false, true, true, true, false,
+        Glyph::new(0, 0, 3),   // space
+        Glyph::new(0, 1, 2),   // !
+        Glyph::new(1, 3, 4),   // "
+        Glyph::new(3, 5, 6),   // #
+        Glyph::new(7, 5, 6),   // $
+        Glyph::new(11, 5, 6),  // %
+        Glyph::new(15, 5, 6),  // &
+        Glyph::new(19, 1, 2),  // '
+        Glyph::new(20, 2, 3),  // (
+        Glyph::new(22, 2, 3),  // )
+        Glyph::new(24, 5, 6),  // *
+        Glyph::new(28, 5, 6),  // +
+        Glyph::new(32, 2, 3),  // ,
+        Glyph::new(34, 3, 4),  // -
+        Glyph::new(36, 1, 2),  // .
+        Glyph::new(37, 5, 6),  // /
+        Glyph::new(41, 5, 6),  // 0
+        Glyph::new(45, 5, 6),  // 1
+        Glyph::new(49, 5, 6),  // 2
+        Glyph::new(53, 5, 6),  // 3
+        Glyph::new(57, 5, 6),  // 4
+        Glyph::new(61, 5, 6),  // 5
+        Glyph::new(65, 5, 6),  // 6
+        Glyph::new(69, 5, 6),  // 7
+        Glyph::new(73, 5, 6),  // 8
+        Glyph::new(77, 5, 6),  // 9
+        Glyph::new(81, 1, 2),  // :
+        Glyph::new(82, 2, 3),  // ;
+        Glyph::new(84, 3, 4),  // <
+        Glyph::new(86, 5, 6),  // =
+        Glyph::new(90, 3, 4),  // >
+        Glyph::new(92, 5, 6),  // ?
+        Glyph::new(96, 5, 6),  // @
+        Glyph::new(100, 5, 6), // A
+        Glyph::new(104, 5, 6), // B
+        Glyph::new(108, 5, 6), // C
+        Glyph::new(112, 5, 6), // D
+        Glyph::new(116, 5, 6), // E
+        Glyph::new(120, 5, 6), // F
+        Glyph::new(124, 5, 6), // G
+        Glyph::new(128, 5, 6), // H
+        Glyph::new(132, 3, 4), // I
+        Glyph::new(134, 5, 6), // J
+        Glyph::new(138, 5, 6), // K
+        Glyph::new(142, 5, 6), // L
+        Glyph::new(146, 5, 6), // M
+        Glyph::new(150, 5, 6), // N
+        Glyph::new(154, 5, 6), // O
+        Glyph::new(158, 5, 6), // P
+        Glyph::new(162, 5, 6), // Q
+        Glyph::new(166, 5, 6), // R
+        Glyph::new(170, 5, 6), // S
+        Glyph::new(174, 5, 6), // T
+        Glyph::new(178, 5, 6), // U
+        Glyph::new(182, 5, 6), // V
+        Glyph::new(186, 5, 6), // W
+        Glyph::new(190, 5, 6), // X
+        Glyph::new(194, 5, 6), // Y
+        Glyph::new(198, 5, 6), // Z
+        Glyph::new(202, 3, 4), // [
+        Glyph::new(204, 5, 6), // \
+        Glyph::new(208, 3, 4), // ]
+        Glyph::new(210, 5, 6), // ^
+        Glyph::new(214, 5, 6), // _
+        Glyph::new(218, 2, 3), // `
+        Glyph::new(220, 3, 4), // {
+        Glyph::new(222, 1, 2), // |
+        Glyph::new(223, 3, 4), // }
+        Glyph::new(225, 5, 6), // ~
     ],
-    // 7
     &[
-        true, true, true, true, true, false, false, false, false, true, false, false, false, true,
-        false, false, false, true, false, false, false, false, true, false, false,
+        0xe8, // !
+        0xb4, 0x00, // "
+        0x57, 0xd5, 0xf5, 0x00, // #
+        0x7d, 0x1c, 0x5f, 0x00, // $
+        0xce, 0x88, 0xb9, 0x80, // %
+        0x64, 0x9b, 0x26, 0x80, // &
+        0xc0, // '
+        0x6a, 0x40, // (
+        0x95, 0x80, // )
+        0xab, 0xbe, 0xea, 0x80, // *
+        0x21, 0x3e, 0x42, 0x00, // +
+        0x01, 0x80, // ,
+        0x03, 0x80, // -
+        0x08, // .
+        0x08, 0x88, 0x88, 0x00, // /
+        0x74, 0x63, 0x17, 0x00, // 0
+        0x27, 0x08, 0x4f, 0x80, // 1
+        0xf0, 0x5d, 0x0f, 0x80, // 2
+        0xf0, 0x7c, 0x1f, 0x00, // 3
+        0x94, 0xa5, 0xf1, 0x00, // 4
+        0xfc, 0x3c, 0x1f, 0x00, // 5
+        0x74, 0x3d, 0x17, 0x00, // 6
+        0xf8, 0x44, 0x42, 0x00, // 7
+        0x74, 0x5d, 0x17, 0x00, // 8
+        0x74, 0x5e, 0x17, 0x00, // 9
+        0x50, // :
+        0x11, 0x80, // ;
+        0x2a, 0x22, // <
+        0x07, 0xc1, 0xf0, 0x00, // =
+        0x88, 0xa8, // >
+        0x74, 0x4c, 0x02, 0x00, // ?
+        0x75, 0xef, 0x07, 0x00, // @
+        0x74, 0x7f, 0x18, 0x80, // A
+        0xf4, 0x7d, 0x1f, 0x00, // B
+        0x7c, 0x21, 0x07, 0x80, // C
+        0xf4, 0x63, 0x1f, 0x00, // D
+        0xfc, 0x3d, 0x0f, 0x80, // E
+        0xfc, 0x3d, 0x08, 0x00, // F
+        0x7c, 0x27, 0x17, 0x00, // G
+        0x8c, 0x7f, 0x18, 0x80, // H
+        0xe9, 0x2e, // I
+        0xf8, 0x85, 0x26, 0x00, // J
+        0x8c, 0xb9, 0x28, 0x80, // K
+        0x84, 0x21, 0x0f, 0x80, // L
+        0x8e, 0xeb, 0x18, 0x80, // M
+        0x8e, 0x6b, 0x38, 0x80, // N
+        0x74, 0x63, 0x17, 0x00, // O
+        0xf4, 0x7d, 0x08, 0x00, // P
+        0x74, 0x6b, 0x26, 0x80, // Q
+        0xf4, 0x7d, 0x28, 0x80, // R
+        0x7c, 0x1c, 0x1f, 0x00, // S
+        0xf9, 0x08, 0x42, 0x00, // T
+        0x8c, 0x63, 0x17, 0x00, // U
+        0x8c, 0x62, 0xa2, 0x00, // V
+        0x8c, 0x6b, 0xb8, 0x80, // W
+        0x8a, 0x88, 0xa8, 0x80, // X
+        0x8a, 0x88, 0x42, 0x00, // Y
+        0xf8, 0x88, 0x8f, 0x80, // Z
+        0xf2, 0x4e, // [
+        0x82, 0x08, 0x20, 0x80, // \
+        0xe4, 0x9e, // ]
+        0x22, 0xa2, 0x00, 0x00, // ^
+        0x00, 0x00, 0x0f, 0x80, // _
+        0x90, 0x00, // `
+        0x6a, 0x26, // {
+        0xf8, // |
+        0xc8, 0xac, // }
+        0x02, 0x2a, 0x20, 0x00, // ~
     ],
-    // 8
+);
+
+/// A compact 3x5 pixel font, which fits three digits across the display.
+pub static FONT_3X5: Font = Font::new(
+    5,
+    5,
+    &[GlyphRange::new(' ', '`', 0), GlyphRange::new('{', '~', 65)],
     &[
-        false, true, true, true, false, true, false, false, false, true, false, true, true, true,
-        false, true, false, false, false, true, false, true, true, true, false,
+        Glyph::new(0, 0, 2),   // space
+        Glyph::new(0, 1, 2),   // !
+        Glyph::new(1, 3, 4),   // "
+        Glyph::new(3, 3, 4),   // #
+        Glyph::new(5, 3, 4),   // $
+        Glyph::new(7, 3, 4),   // %
+        Glyph::new(9, 3, 4),   // &
+        Glyph::new(11, 1, 2),  // '
+        Glyph::new(12, 2, 3),  // (
+        Glyph::new(14, 2, 3),  // )
+        Glyph::new(16, 3, 4),  // *
+        Glyph::new(18, 3, 4),  // +
+        Glyph::new(20, 2, 3),  // ,
+        Glyph::new(22, 3, 4),  // -
+        Glyph::new(24, 1, 2),  // .
+        Glyph::new(25, 3, 4),  // /
+        Glyph::new(27, 3, 4),  // 0
+        Glyph::new(29, 3, 4),  // 1
+        Glyph::new(31, 3, 4),  // 2
+        Glyph::new(33, 3, 4),  // 3
+        Glyph::new(35, 3, 4),  // 4
+        Glyph::new(37, 3, 4),  // 5
+        Glyph::new(39, 3, 4),  // 6
+        Glyph::new(41, 3, 4),  // 7
+        Glyph::new(43, 3, 4),  // 8
+        Glyph::new(45, 3, 4),  // 9
+        Glyph::new(47, 1, 2),  // :
+        Glyph::new(48, 2, 3),  // ;
+        Glyph::new(50, 3, 4),  // <
+        Glyph::new(52, 3, 4),  // =
+        Glyph::new(54, 3, 4),  // >
+        Glyph::new(56, 3, 4),  // ?
+        Glyph::new(58, 3, 4),  // @
+        Glyph::new(60, 3, 4),  // A
+        Glyph::new(62, 3, 4),  // B
+        Glyph::new(64, 3, 4),  // C
+        Glyph::new(66, 3, 4),  // D
+        Glyph::new(68, 3, 4),  // E
+        Glyph::new(70, 3, 4),  // F
+        Glyph::new(72, 3, 4),  // G
+        Glyph::new(74, 3, 4),  // H
+        Glyph::new(76, 3, 4),  // I
+        Glyph::new(78, 3, 4),  // J
+        Glyph::new(80, 3, 4),  // K
+        Glyph::new(82, 3, 4),  // L
+        Glyph::new(84, 3, 4),  // M
+        Glyph::new(86, 3, 4),  // N
+        Glyph::new(88, 3, 4),  // O
+        Glyph::new(90, 3, 4),  // P
+        Glyph::new(92, 3, 4),  // Q
+        Glyph::new(94, 3, 4),  // R
+        Glyph::new(96, 3, 4),  // S
+        Glyph::new(98, 3, 4),  // T
+        Glyph::new(100, 3, 4), // U
+        Glyph::new(102, 3, 4), // V
+        Glyph::new(104, 3, 4), // W
+        Glyph::new(106, 3, 4), // X
+        Glyph::new(108, 3, 4), // Y
+        Glyph::new(110, 3, 4), // Z
+        Glyph::new(112, 2, 3), // [
+        Glyph::new(114, 3, 4), // \
+        Glyph::new(116, 2, 3), // ]
+        Glyph::new(118, 3, 4), // ^
+        Glyph::new(120, 3, 4), // _
+        Glyph::new(122, 2, 3), // `
+        Glyph::new(124, 3, 4), // {
+        Glyph::new(126, 1, 2), // |
+        Glyph::new(127, 3, 4), // }
+        Glyph::new(129, 3, 4), // ~
     ],
-    // 9
     &[
-        false, true, true, true, false, true, false, false, false, true, false, true, true, true,
-        true, false, false, false, false, true, false, true, true, true, false,
+        0xe8, // !
+        0xb4, 0x00, // "
+        0xbe, 0xfa, // #
+        0x79, 0x3c, // $
+        0x85, 0x42, // %
+        0x55, 0x56, // &
+        0xc0, // '
+        0x6a, 0x40, // (
+        0x95, 0x80, // )
+        0x15, 0x50, // *
+        0x0b, 0xa0, // +
+        0x01, 0x80, // ,
+        0x03, 0x80, // -
+        0x08, // .
+        0x25, 0x48, // /
+        0xf6, 0xde, // 0
+        0x59, 0x2e, // 1
+        0xe7, 0xce, // 2
+        0xe5, 0x9e, // 3
+        0xb7, 0x92, // 4
+        0xf3, 0x9e, // 5
+        0xf3, 0xde, // 6
+        0xe4, 0xa4, // 7
+        0xf7, 0xde, // 8
+        0xf7, 0x9e, // 9
+        0x50, // :
+        0x11, 0x80, // ;
+        0x2a, 0x22, // <
+        0x1c, 0x70, // =
+        0x88, 0xa8, // >
+        0xe5, 0x04, // ?
+        0x57, 0xc6, // @
+        0x57, 0xda, // A
+        0xd7, 0x5c, // B
+        0x72, 0x46, // C
+        0xd6, 0xdc, // D
+        0xf3, 0xce, // E
+        0xf3, 0xc8, // F
+        0x72, 0xd6, // G
+        0xb7, 0xda, // H
+        0xe9, 0x2e, // I
+        0x24, 0xd4, // J
+        0xb7, 0x5a, // K
+        0x92, 0x4e, // L
+        0xbf, 0xda, // M
+        0xd6, 0xda, // N
+        0x56, 0xd4, // O
+        0xd7, 0x48, // P
+        0x56, 0xf6, // Q
+        0xd7, 0x5a, // R
+        0x71, 0x1c, // S
+        0xe9, 0x24, // T
+        0xb6, 0xde, // U
+        0xb6, 0xd4, // V
+        0xb7, 0xfa, // W
+        0xb5, 0x5a, // X
+        0xb5, 0x24, // Y
+        0xe5, 0x4e, // Z
+        0xea, 0xc0, // [
+        0x91, 0x12, // \
+        0xd5, 0xc0, // ]
+        0x54, 0x00, // ^
+        0x00, 0x0e, // _
+        0x90, 0x00, // `
+        0x6a, 0x26, // {
+        0xf8, // |
+        0xc8, 0xac, // }
+        0x01, 0xe0, // ~
     ],
-];
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+    use crate::text::Alignment;
+    use crate::WHITE;
+
+    #[test]
+    fn space_is_blank() {
+        for font in [&FONT_5X5, &FONT_3X5] {
+            let space = font.glyph(' ').unwrap();
+            assert_eq!(space.width(), 0);
+            assert!(space.advance() > 0);
+
+            let mut fb = Framebuffer::new();
+            assert_eq!(fb.draw_char(' ', font, (0, 0), WHITE), space.advance());
+            fb.draw_text("   ", font, (0, 6), Alignment::Left, WHITE);
+            assert_eq!(fb, Framebuffer::new());
+        }
+    }
+
+    #[test]
+    fn space_only_moves_the_next_character() {
+        for font in [&FONT_5X5, &FONT_3X5] {
+            let mut text = Framebuffer::new();
+            text.draw_text("A B", font, (0, 0), Alignment::Left, WHITE);
+
+            let mut chars = Framebuffer::new();
+            let advance = chars.draw_char('A', font, (0, 0), WHITE);
+            let space = font.glyph(' ').unwrap().advance();
+            chars.draw_char('B', font, ((advance + space) as i32, 0), WHITE);
+
+            assert_eq!(text, chars);
+        }
+    }
+
+    /// Check a glyph against rows of `#` for pixels that are on and `.` for pixels that are off.
+    fn assert_glyph(glyph: GlyphBitmap, rows: &[&str]) {
+        assert_eq!(glyph.height(), rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            assert_eq!(glyph.width(), row.len());
+            for (x, c) in row.chars().enumerate() {
+                assert_eq!(glyph.pixel(x, y), c == '#', "pixel ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn glyph_bitmaps() {
+        assert_glyph(
+            FONT_3X5.glyph('A').unwrap(),
+            &[".#.", "#.#", "###", "#.#", "#.#"],
+        );
+        assert_glyph(
+            FONT_5X5.glyph('+').unwrap(),
+            &["..#..", "..#..", "#####", "..#..", "..#.."],
+        );
+
+        // pixels outside the glyph are off
+        let glyph = FONT_5X5.glyph('+').unwrap();
+        assert!(!glyph.pixel(5, 2));
+        assert!(!glyph.pixel(2, 5));
+    }
+
+    #[test]
+    fn lowercase_falls_back_to_uppercase() {
+        for font in [&FONT_5X5, &FONT_3X5] {
+            for c in 'a'..='z' {
+                let mut lower = Framebuffer::new();
+                let mut upper = Framebuffer::new();
+                let advance = lower.draw_char(c, font, (0, 0), WHITE);
+                assert_eq!(
+                    upper.draw_char(c.to_ascii_uppercase(), font, (0, 0), WHITE),
+                    advance
+                );
+                assert!(advance > 0);
+                assert_eq!(lower, upper, "{c}");
+            }
+            assert_eq!(font.measure_text("score"), font.measure_text("SCORE"));
+        }
+    }
+
+    #[test]
+    fn unknown_characters_draw_nothing() {
+        for font in [&FONT_5X5, &FONT_3X5] {
+            for c in ['\u{e9}', '\u{7f}', '\n', '\u{1f600}'] {
+                assert!(font.glyph(c).is_none());
+
+                let mut fb = Framebuffer::new();
+                assert_eq!(fb.draw_char(c, font, (0, 0), WHITE), 0);
+                assert_eq!(fb, Framebuffer::new());
+            }
+
+            // they are skipped in text, without leaving a gap
+            assert_eq!(font.measure_text("A\u{e9}B"), font.measure_text("AB"));
+        }
+    }
+
+    #[test]
+    fn digit_widths() {
+        // three narrow digits fit across the display
+        for n in ["000", "888", "123"] {
+            assert!(FONT_3X5.measure_text(n) <= 12, "{n}");
+        }
+
+        // every digit has the same width and advance so numbers line up
+        for font in [&FONT_5X5, &FONT_3X5] {
+            let zero = font.glyph('0').unwrap();
+            for c in '1'..='9' {
+                let digit = font.glyph(c).unwrap();
+                assert_eq!(digit.width(), zero.width(), "{c}");
+                assert_eq!(digit.advance(), zero.advance(), "{c}");
+            }
+        }
+    }
+}
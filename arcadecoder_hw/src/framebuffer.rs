@@ -0,0 +1,553 @@
+//! Hardware-independent display buffer
+//!
+//! The [`Framebuffer`] holds the packed pixel data in the layout expected by the ICN2012 and HC595 shift registers on
+//! the Arcade Coder. It has no dependency on the ESP32 so rendering code can be built and tested on any host.
+//!
+//! The display is driven as 6 channels, each lighting two physical rows (`y` and `y + 6`). Each channel is 9 bytes:
+//! three groups of green, red and blue bytes where a `0` bit turns the LED on.
+
+use crate::font::{measure_glyphs, Font, GlyphBitmap};
+use crate::geometry::{Point, Rect};
+use crate::text::{Alignment, Digits};
+use crate::{orientation::Orientation, Color, Coordinates};
+
+/// Width of the display in pixels.
+pub const WIDTH: usize = 12;
+
+/// Height of the display in pixels.
+pub const HEIGHT: usize = 12;
+
+/// Number of channels that are multiplexed to drive the display.
+pub const CHANNELS: usize = 6;
+
+/// Number of bytes shifted out for each channel.
+pub const ROW_BYTES: usize = 9;
+
+/// Packed data for a single channel.
+pub type Row = [u8; ROW_BYTES];
+
+/// Unpacked colors for every pixel, indexed as `[y][x]`.
+pub type ColorGrid = [[Color; WIDTH]; HEIGHT];
+
+/// A packed 12x12 3-bit display buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    rows: [Row; CHANNELS],
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Framebuffer {
+    /// Create a new blank framebuffer.
+    pub const fn new() -> Self {
+        Self {
+            // all bits on is a blank display
+            rows: [[0xff; ROW_BYTES]; CHANNELS],
+        }
+    }
+
+    /// Clear the framebuffer to make the screen blank.
+    pub fn clear(&mut self) {
+        self.rows = [[0xff; ROW_BYTES]; CHANNELS];
+    }
+
+    /// Set every pixel to a color.
+    pub fn fill(&mut self, color: Color) {
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                self.set_pixel((x, y), color);
+            }
+        }
+    }
+
+    /// Set a pixel to a color
+    ///
+    /// _Indexing starts from 0, so (0, 0) is the top-left and (11, 11) is the bottom-right._ Pixels off the display are
+    /// ignored.
+    pub fn set_pixel(&mut self, pos: impl Into<Point>, color: Color) {
+        // if the coordinates are out of bounds, do nothing
+        let Some(pos) = pos.into().to_coordinates() else {
+            return;
+        };
+
+        // calculate the byte and bit to be changed
+        let (byte_idx, bit_idx) = get_display_indexes(pos);
+        let row = &mut self.rows[pos.1 % CHANNELS];
+
+        // set the green, red and blue values respectively
+        // each value is in the next byte so just need to add 1 and 2 to the byte index
+        let channels = [color.green(), color.red(), color.blue()];
+        for (byte, on) in row[byte_idx..byte_idx + 3].iter_mut().zip(channels) {
+            *byte = *byte & !(1 << bit_idx) | (u8::from(!on) << bit_idx);
+        }
+    }
+
+    /// Get the color of a pixel, or `None` if the coordinates are out of bounds.
+    pub fn get_pixel(&self, pos: impl Into<Point>) -> Option<Color> {
+        let pos = pos.into().to_coordinates()?;
+
+        let (byte_idx, bit_idx) = get_display_indexes(pos);
+        let row = &self.rows[pos.1 % CHANNELS];
+        let bit_on = |byte: u8| byte & (1 << bit_idx) == 0;
+
+        Some(Color::from_rgb(
+            bit_on(row[byte_idx + 1]),
+            bit_on(row[byte_idx]),
+            bit_on(row[byte_idx + 2]),
+        ))
+    }
+
+    /// Iterate over the coordinates and color of every pixel, row by row from the top-left.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{framebuffer::Framebuffer, BLACK, RED};
+    ///
+    /// let mut fb = Framebuffer::new();
+    /// fb.set_pixel((3, 7), RED);
+    ///
+    /// let lit: Vec<_> = fb.pixels().filter(|(_, color)| *color != BLACK).collect();
+    /// assert_eq!(lit, [((3, 7), RED)]);
+    /// ```
+    pub fn pixels(&self) -> impl Iterator<Item = (Coordinates, Color)> + '_ {
+        (0..HEIGHT).flat_map(move |y| {
+            (0..WIDTH).map(move |x| {
+                // always in bounds
+                let color = self.get_pixel((x, y)).unwrap_or_default();
+                ((x, y), color)
+            })
+        })
+    }
+
+    /// Unpack the color of every pixel.
+    pub fn to_colors(&self) -> ColorGrid {
+        let mut colors = [[Color::default(); WIDTH]; HEIGHT];
+        for ((x, y), color) in self.pixels() {
+            colors[y][x] = color;
+        }
+        colors
+    }
+
+    /// Create a framebuffer from the color of every pixel.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{framebuffer::Framebuffer, BLACK, GREEN};
+    ///
+    /// let mut colors = [[BLACK; 12]; 12];
+    /// colors[0][5] = GREEN;
+    ///
+    /// let fb = Framebuffer::from_colors(&colors);
+    /// assert_eq!(fb.get_pixel((5, 0)), Some(GREEN));
+    /// assert_eq!(fb.to_colors(), colors);
+    /// ```
+    pub fn from_colors(colors: &ColorGrid) -> Self {
+        let mut fb = Self::new();
+        for (y, row) in colors.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                fb.set_pixel((x, y), *color);
+            }
+        }
+        fb
+    }
+
+    /// Get a copy of the framebuffer in the panel's native layout, for a frame drawn in `orientation`.
+    pub fn oriented(&self, orientation: Orientation) -> Self {
+        if orientation == Orientation::NATIVE {
+            return *self;
+        }
+
+        let mut fb = Self::new();
+        for (pos, color) in self.pixels() {
+            fb.set_pixel(orientation.to_native(pos), color);
+        }
+        fb
+    }
+
+    /// Get the packed data for a channel.
+    pub fn row(&self, channel: usize) -> &Row {
+        &self.rows[channel]
+    }
+
+    /// Get mutable access to the packed data for a channel.
+    pub fn row_mut(&mut self, channel: usize) -> &mut Row {
+        &mut self.rows[channel]
+    }
+
+    /// Get the packed data for all channels.
+    pub fn rows(&self) -> &[Row; CHANNELS] {
+        &self.rows
+    }
+
+    /// Draw a filled rectangle between two opposite corners (inclusive), in any order.
+    pub fn draw_rect(&mut self, pos1: impl Into<Point>, pos2: impl Into<Point>, color: Color) {
+        // only visit the part of the rectangle that is on the display
+        let rect = Rect::from_corners(pos1, pos2);
+        let Some(rect) = rect.intersection(&Rect::DISPLAY) else {
+            return;
+        };
+
+        for y in rect.y..rect.y + rect.height as i32 {
+            for x in rect.x..rect.x + rect.width as i32 {
+                self.set_pixel((x, y), color);
+            }
+        }
+    }
+
+    /// Draw a glyph with its top-left corner at a position, clipping any pixels outside `clip`.
+    fn draw_glyph(&mut self, glyph: &GlyphBitmap, start_pos: Point, clip: Rect, color: Color) {
+        for row in 0..glyph.height() {
+            for col in 0..glyph.width() {
+                let pos = start_pos.offset(col as i32, row as i32);
+                if glyph.pixel(col, row) && clip.contains(pos) {
+                    self.set_pixel(pos, color);
+                }
+            }
+        }
+    }
+
+    /// Draw glyphs one after another from a position, skipping the glyphs that are not visible.
+    fn draw_glyphs<'a>(
+        &mut self,
+        glyphs: impl Iterator<Item = (GlyphBitmap<'a>, bool)>,
+        start_pos: Point,
+        clip: Rect,
+        color: Color,
+    ) {
+        let mut pos = start_pos;
+        for (glyph, visible) in glyphs {
+            if visible {
+                self.draw_glyph(&glyph, pos, clip, color);
+            }
+            pos = pos.offset(glyph.advance() as i32, 0);
+        }
+    }
+
+    /// Draw a digit from a font
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{font::FONT_5X5, framebuffer::Framebuffer, WHITE};
+    ///
+    /// let mut fb = Framebuffer::new();
+    /// fb.draw_digit(0, &FONT_5X5, (6, 0), WHITE);
+    /// ```
+    pub fn draw_digit(&mut self, n: u32, font: &Font, start_pos: impl Into<Point>, color: Color) {
+        if let Some(digit) = char::from_digit(n % 10, 10) {
+            self.draw_char(digit, font, start_pos, color);
+        }
+    }
+
+    /// Draw a character from a font
+    ///
+    /// Lowercase letters are drawn in uppercase if the font has no lowercase glyphs. Characters that are not in the
+    /// font are skipped.
+    ///
+    /// Returns the advance of the character, which is where the next character should start relative to this one, or
+    /// 0 if the character was skipped.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{font::FONT_5X5, framebuffer::Framebuffer, WHITE};
+    ///
+    /// let mut fb = Framebuffer::new();
+    /// let advance = fb.draw_char('I', &FONT_5X5, (0, 0), WHITE);
+    /// fb.draw_char('?', &FONT_5X5, (advance, 0), WHITE);
+    /// ```
+    pub fn draw_char(
+        &mut self,
+        character: char,
+        font: &Font,
+        start_pos: impl Into<Point>,
+        color: Color,
+    ) -> usize {
+        let Some(glyph) = font.glyph(character) else {
+            return 0;
+        };
+
+        self.draw_glyph(&glyph, start_pos.into(), Rect::DISPLAY, color);
+        glyph.advance()
+    }
+
+    /// Draw a string from a font, aligned to an anchor at the top of the text
+    ///
+    /// Characters that are not in the font are skipped, and anything off the display is clipped.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{font::FONT_3X5, framebuffer::Framebuffer, text::Alignment, WHITE};
+    ///
+    /// let mut fb = Framebuffer::new();
+    /// // centred on the middle of the display
+    /// fb.draw_text("GO!", &FONT_3X5, (6, 3), Alignment::Center, WHITE);
+    /// ```
+    pub fn draw_text(
+        &mut self,
+        text: &str,
+        font: &Font,
+        anchor: impl Into<Point>,
+        alignment: Alignment,
+        color: Color,
+    ) {
+        let anchor = anchor.into();
+        let x = alignment.start(anchor.x, font.measure_text(text));
+        self.draw_text_at(text, font, Point::new(x, anchor.y), Rect::DISPLAY, color);
+    }
+
+    /// Draw a string from a font, aligned within an area starting at its top
+    ///
+    /// Anything outside the area is clipped.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{
+    ///     font::FONT_5X5, framebuffer::Framebuffer, geometry::Rect, text::Alignment, BLACK, WHITE,
+    /// };
+    ///
+    /// let mut fb = Framebuffer::new();
+    /// // the left half of the display, so only the first character fits
+    /// fb.draw_text_in("AB", &FONT_5X5, Rect::new(0, 0, 6, 12), Alignment::Left, WHITE);
+    /// assert!(fb.pixels().all(|((x, _), color)| x < 6 || color == BLACK));
+    /// ```
+    pub fn draw_text_in(
+        &mut self,
+        text: &str,
+        font: &Font,
+        area: Rect,
+        alignment: Alignment,
+        color: Color,
+    ) {
+        let x = alignment.start_in(area, font.measure_text(text));
+        self.draw_text_at(text, font, Point::new(x, area.y), area, color);
+    }
+
+    /// Draw a string from a font with its top-left corner at a position, clipping anything outside `clip`.
+    pub(crate) fn draw_text_at(
+        &mut self,
+        text: &str,
+        font: &Font,
+        start_pos: Point,
+        clip: Rect,
+        color: Color,
+    ) {
+        let glyphs = text
+            .chars()
+            .filter_map(|c| font.glyph(c))
+            .map(|g| (g, true));
+        self.draw_glyphs(glyphs, start_pos, clip, color);
+    }
+
+    /// Draw a number from a font, aligned to an anchor at the top of the number
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::{
+    ///     font::FONT_3X5, framebuffer::Framebuffer, text::{Alignment, Digits}, WHITE,
+    /// };
+    ///
+    /// let mut fb = Framebuffer::new();
+    /// // "07" in the top-right corner
+    /// fb.draw_number(7, Digits::Zeros(2), &FONT_3X5, (11, 0), Alignment::Right, WHITE);
+    /// ```
+    pub fn draw_number(
+        &mut self,
+        n: u32,
+        digits: Digits,
+        font: &Font,
+        anchor: impl Into<Point>,
+        alignment: Alignment,
+        color: Color,
+    ) {
+        // blank digits are still measured so the number is aligned as if they were drawn
+        let glyphs =
+            number_chars(n, digits).filter_map(|(c, visible)| Some((font.glyph(c)?, visible)));
+        let width = measure_glyphs(glyphs.clone().map(|(glyph, _)| glyph));
+        let anchor = anchor.into();
+        let x = alignment.start(anchor.x, width);
+        self.draw_glyphs(glyphs, Point::new(x, anchor.y), Rect::DISPLAY, color);
+    }
+}
+
+impl From<ColorGrid> for Framebuffer {
+    fn from(colors: ColorGrid) -> Self {
+        Self::from_colors(&colors)
+    }
+}
+
+impl From<Framebuffer> for ColorGrid {
+    fn from(fb: Framebuffer) -> Self {
+        fb.to_colors()
+    }
+}
+
+/// Get the digits of a number from the left, with whether each one is drawn.
+fn number_chars(n: u32, digits: Digits) -> impl Iterator<Item = (char, bool)> + Clone {
+    let (count, zeros) = match digits {
+        Digits::Zeros(count) => (count, true),
+        Digits::Blank(count) => (count, false),
+    };
+    let len = n.checked_ilog10().unwrap_or(0) + 1;
+
+    (0..len.max(count.into())).rev().map(move |place| {
+        let digit = 10_u32.checked_pow(place).map_or(0, |power| n / power % 10);
+        (char::from(b'0' + digit as u8), zeros || place < len)
+    })
+}
+
+/// Get the byte and bit index within a channel's data for a pixel.
+///
+/// The byte index points at the green byte, with red and blue in the following two bytes.
+pub(crate) fn get_display_indexes(pos: Coordinates) -> (usize, usize) {
+    match (pos.0 < 4, pos.1 < 6) {
+        (true, true) => (3, 4 + pos.0),
+        (true, false) => (3, pos.0),
+        (false, true) => (0, pos.0 - 4),
+        (false, false) => (6, pos.0 - 4),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BLACK, BLUE, GREEN, MAGENTA, RED, WHITE};
+
+    /// Check that a pixel sets exactly the expected green, red and blue bits (a `0` is on) in its channel.
+    fn assert_packed(pos: Coordinates, byte_idx: usize, bit_idx: usize, color: Color) {
+        let mut fb = Framebuffer::new();
+        fb.set_pixel(pos, color);
+
+        let mut expected = [[0xff; ROW_BYTES]; CHANNELS];
+        let row = &mut expected[pos.1 % CHANNELS];
+        for (offset, on) in [color.green(), color.red(), color.blue()]
+            .into_iter()
+            .enumerate()
+        {
+            if on {
+                row[byte_idx + offset] &= !(1 << bit_idx);
+            }
+        }
+
+        assert_eq!(fb.rows(), &expected, "pixel {pos:?}");
+        assert_eq!(fb.get_pixel(pos), Some(color));
+    }
+
+    #[test]
+    fn packs_pixel_in_each_quadrant() {
+        // top-left, bottom-left, top-right, bottom-right
+        assert_packed((1, 2), 3, 5, RED);
+        assert_packed((2, 8), 3, 2, GREEN);
+        assert_packed((7, 3), 0, 3, BLUE);
+        assert_packed((10, 9), 6, 6, MAGENTA);
+        assert_packed((0, 0), 3, 4, WHITE);
+        assert_packed((11, 11), 6, 7, WHITE);
+    }
+
+    #[test]
+    fn packs_channels_in_green_red_blue_order() {
+        let mut fb = Framebuffer::new();
+        fb.set_pixel((4, 0), RED);
+        assert_eq!(fb.row(0)[..3], [0xff, 0xfe, 0xff]);
+
+        fb.set_pixel((4, 0), GREEN);
+        assert_eq!(fb.row(0)[..3], [0xfe, 0xff, 0xff]);
+
+        fb.set_pixel((4, 0), BLUE);
+        assert_eq!(fb.row(0)[..3], [0xff, 0xff, 0xfe]);
+    }
+
+    #[test]
+    fn set_and_get_round_trip() {
+        let mut fb = Framebuffer::new();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                fb.set_pixel((x, y), Color::from_bits((x + y) as u8));
+            }
+        }
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                assert_eq!(fb.get_pixel((x, y)), Some(Color::from_bits((x + y) as u8)));
+            }
+        }
+
+        // overwriting a pixel only changes that pixel
+        fb.set_pixel((5, 5), BLACK);
+        assert_eq!(fb.get_pixel((5, 5)), Some(BLACK));
+        assert_eq!(fb.get_pixel((6, 5)), Some(Color::from_bits(11)));
+    }
+
+    #[test]
+    fn ignores_pixels_off_the_display() {
+        let mut fb = Framebuffer::new();
+        fb.set_pixel((12, 0), WHITE);
+        fb.set_pixel((0, 12), WHITE);
+        fb.set_pixel((-1, 3), WHITE);
+
+        assert_eq!(fb, Framebuffer::new());
+        assert_eq!(fb.get_pixel((12, 0)), None);
+        assert_eq!(fb.get_pixel((0, -1)), None);
+    }
+
+    #[test]
+    fn fill_and_clear() {
+        let mut fb = Framebuffer::new();
+        fb.fill(WHITE);
+        assert!(fb.rows().iter().flatten().all(|byte| *byte == 0x00));
+        assert!(fb.pixels().all(|(_, color)| color == WHITE));
+
+        fb.fill(RED);
+        assert!(fb.pixels().all(|(_, color)| color == RED));
+
+        fb.clear();
+        assert_eq!(fb, Framebuffer::new());
+        assert!(fb.rows().iter().flatten().all(|byte| *byte == 0xff));
+    }
+
+    /// Get a grid with a different color for each pixel.
+    fn color_grid() -> ColorGrid {
+        let mut colors = [[BLACK; WIDTH]; HEIGHT];
+        for (y, row) in colors.iter_mut().enumerate() {
+            for (x, color) in row.iter_mut().enumerate() {
+                *color = Color::from_bits((x * 3 + y * 5) as u8);
+            }
+        }
+        colors
+    }
+
+    #[test]
+    fn pixels_go_row_by_row_from_the_top_left() {
+        let colors = color_grid();
+        let fb = Framebuffer::from_colors(&colors);
+
+        for (i, (pos, color)) in fb.pixels().enumerate() {
+            assert_eq!(pos, (i % WIDTH, i / WIDTH));
+            assert_eq!(color, colors[pos.1][pos.0]);
+        }
+        assert_eq!(fb.pixels().count(), WIDTH * HEIGHT);
+    }
+
+    #[test]
+    fn color_grid_round_trip() {
+        let colors = color_grid();
+
+        let fb = Framebuffer::from_colors(&colors);
+        assert_eq!(fb.to_colors(), colors);
+        for ((x, y), color) in fb.pixels() {
+            assert_eq!(fb.get_pixel((x, y)), Some(color));
+        }
+
+        // the From conversions match
+        let from: Framebuffer = colors.into();
+        assert_eq!(from, fb);
+        assert_eq!(ColorGrid::from(from), colors);
+
+        // and packing is lossless in the other direction too
+        let mut fb = Framebuffer::new();
+        fb.fill(RED);
+        fb.set_pixel((11, 0), BLUE);
+        assert_eq!(Framebuffer::from_colors(&fb.to_colors()), fb);
+    }
+}
@@ -0,0 +1,236 @@
+//! Positions and areas that can be off the display
+//!
+//! Drawing functions take a signed [`Point`], so shapes, text and sprites can start left of or above the display and be
+//! partly drawn. Anything that accepts a point also accepts `(x, y)` tuples of `i32` or [`Coordinates`], so
+//! `fb.set_pixel((3, 7), RED)` still works.
+//!
+//! Pixels are clipped in one place: a point is only drawn if [`Point::to_coordinates`] finds it on the display, and
+//! functions that draw within an area also check [`Rect::contains`].
+
+use crate::framebuffer::{HEIGHT, WIDTH};
+use crate::Coordinates;
+
+/// A position that can be anywhere, including off the display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Point {
+    /// Distance from the left of the display.
+    pub x: i32,
+
+    /// Distance from the top of the display.
+    pub y: i32,
+}
+
+/// A rectangular area that can be partly or completely off the display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    /// Left edge of the rectangle.
+    pub x: i32,
+
+    /// Top edge of the rectangle.
+    pub y: i32,
+
+    /// Width of the rectangle in pixels.
+    pub width: u32,
+
+    /// Height of the rectangle in pixels.
+    pub height: u32,
+}
+
+impl Point {
+    /// The top-left of the display.
+    pub const ORIGIN: Self = Self::new(0, 0);
+
+    /// Create a point.
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Get the point moved by an amount in each direction, stopping at the limits of `i32`.
+    pub const fn offset(self, dx: i32, dy: i32) -> Self {
+        Self::new(self.x.saturating_add(dx), self.y.saturating_add(dy))
+    }
+
+    /// Get the display coordinates of the point, or `None` if it is off the display.
+    pub fn to_coordinates(self) -> Option<Coordinates> {
+        let (x, y) = (usize::try_from(self.x).ok()?, usize::try_from(self.y).ok()?);
+        (x < WIDTH && y < HEIGHT).then_some((x, y))
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl From<Coordinates> for Point {
+    /// Coordinates too large for an `i32` are so far off the display that they are moved to the largest `i32`.
+    fn from((x, y): Coordinates) -> Self {
+        let to_i32 = |n: usize| i32::try_from(n).unwrap_or(i32::MAX);
+        Self::new(to_i32(x), to_i32(y))
+    }
+}
+
+impl From<Point> for (i32, i32) {
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+impl Rect {
+    /// The whole display.
+    pub const DISPLAY: Self = Self::new(0, 0, WIDTH as u32, HEIGHT as u32);
+
+    /// Create a rectangle from its top-left corner and size.
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Create a rectangle between two opposite corners (inclusive), in any order.
+    pub fn from_corners(pos1: impl Into<Point>, pos2: impl Into<Point>) -> Self {
+        let (pos1, pos2) = (pos1.into(), pos2.into());
+        Self::new(
+            pos1.x.min(pos2.x),
+            pos1.y.min(pos2.y),
+            pos1.x.abs_diff(pos2.x).saturating_add(1),
+            pos1.y.abs_diff(pos2.y).saturating_add(1),
+        )
+    }
+
+    /// The top-left corner of the rectangle.
+    pub const fn top_left(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    /// Whether a point is inside the rectangle.
+    pub fn contains(&self, point: impl Into<Point>) -> bool {
+        let point = point.into();
+        let inside = |start: i32, size: u32, n: i32| {
+            (i64::from(start)..i64::from(start) + i64::from(size)).contains(&i64::from(n))
+        };
+        inside(self.x, self.width, point.x) && inside(self.y, self.height, point.y)
+    }
+
+    /// Get the area covered by both rectangles, or `None` if they do not overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let overlap = |start1: i32, size1: u32, start2: i32, size2: u32| {
+            let start = start1.max(start2);
+            let end =
+                (i64::from(start1) + i64::from(size1)).min(i64::from(start2) + i64::from(size2));
+            let size = u32::try_from(end - i64::from(start))
+                .ok()
+                .filter(|size| *size > 0)?;
+            Some((start, size))
+        };
+
+        let (x, width) = overlap(self.x, self.width, other.x, other.width)?;
+        let (y, height) = overlap(self.y, self.height, other.y, other.height)?;
+        Some(Self::new(x, y, width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_to_coordinates() {
+        assert_eq!(Point::new(0, 0).to_coordinates(), Some((0, 0)));
+        assert_eq!(Point::new(11, 11).to_coordinates(), Some((11, 11)));
+        assert_eq!(Point::new(-1, 0).to_coordinates(), None);
+        assert_eq!(Point::new(0, -1).to_coordinates(), None);
+        assert_eq!(Point::new(12, 0).to_coordinates(), None);
+        assert_eq!(Point::new(0, i32::MIN).to_coordinates(), None);
+    }
+
+    #[test]
+    fn point_conversions() {
+        assert_eq!(Point::from((-3, 4)), Point::new(-3, 4));
+        assert_eq!(<(i32, i32)>::from(Point::new(-3, 4)), (-3, 4));
+        assert_eq!(Point::from((5_usize, 6_usize)), Point::new(5, 6));
+        assert_eq!(Point::from((usize::MAX, 1_usize)), Point::new(i32::MAX, 1));
+        assert_eq!(
+            Point::new(i32::MAX, i32::MIN).offset(1, -1),
+            Point::new(i32::MAX, i32::MIN)
+        );
+    }
+
+    #[test]
+    fn contains_with_negative_coordinates() {
+        let rect = Rect::new(-3, -2, 4, 3);
+        assert!(rect.contains((-3, -2)));
+        assert!(rect.contains((0, 0)));
+        assert!(rect.contains((-1, -1)));
+        assert!(!rect.contains((1, 0)));
+        assert!(!rect.contains((0, 1)));
+        assert!(!rect.contains((-4, -2)));
+        assert!(!rect.contains((-3, -3)));
+
+        // an empty rectangle contains nothing
+        assert!(!Rect::new(-1, -1, 0, 5).contains((-1, -1)));
+
+        // rectangles that reach past the limits of i32
+        let huge = Rect::new(i32::MIN, i32::MIN, u32::MAX, u32::MAX);
+        assert!(huge.contains((i32::MIN, i32::MIN)));
+        assert!(huge.contains((i32::MAX - 1, 0)));
+        assert!(!huge.contains((i32::MAX, 0)));
+        assert!(Rect::new(i32::MAX, 0, 10, 1).contains((i32::MAX, 0)));
+    }
+
+    #[test]
+    fn from_corners_in_any_order() {
+        let rect = Rect::new(-2, -5, 5, 8);
+        assert_eq!(Rect::from_corners((-2, -5), (2, 2)), rect);
+        assert_eq!(Rect::from_corners((2, 2), (-2, -5)), rect);
+        assert_eq!(Rect::from_corners((-2, 2), (2, -5)), rect);
+        assert_eq!(Rect::from_corners((1, 1), (1, 1)), Rect::new(1, 1, 1, 1));
+        assert_eq!(
+            Rect::from_corners((i32::MIN, 0), (i32::MAX, 0)),
+            Rect::new(i32::MIN, 0, u32::MAX, 1)
+        );
+    }
+
+    #[test]
+    fn intersection_with_negative_coordinates() {
+        // partly off the top-left of the display
+        assert_eq!(
+            Rect::new(-3, -2, 5, 4).intersection(&Rect::DISPLAY),
+            Some(Rect::new(0, 0, 2, 2))
+        );
+
+        // both off the display
+        let a = Rect::new(-10, -10, 6, 6);
+        let b = Rect::new(-7, -8, 10, 3);
+        assert_eq!(a.intersection(&b), Some(Rect::new(-7, -8, 3, 3)));
+        assert_eq!(b.intersection(&a), a.intersection(&b));
+
+        // one inside the other
+        assert_eq!(
+            Rect::DISPLAY.intersection(&Rect::new(-20, -20, 50, 50)),
+            Some(Rect::DISPLAY)
+        );
+    }
+
+    #[test]
+    fn intersection_without_overlap() {
+        // touching edges do not overlap
+        assert_eq!(Rect::new(-3, 0, 3, 5).intersection(&Rect::DISPLAY), None);
+        assert_eq!(Rect::new(0, -5, 5, 5).intersection(&Rect::DISPLAY), None);
+        assert_eq!(Rect::new(-3, -3, 2, 2).intersection(&Rect::DISPLAY), None);
+        assert_eq!(Rect::new(12, 0, 5, 5).intersection(&Rect::DISPLAY), None);
+
+        // empty rectangles overlap nothing
+        assert_eq!(Rect::new(2, 2, 0, 5).intersection(&Rect::DISPLAY), None);
+
+        // far apart at the limits of i32
+        assert_eq!(
+            Rect::new(i32::MIN, i32::MIN, 1, 1).intersection(&Rect::new(i32::MAX, i32::MAX, 1, 1)),
+            None
+        );
+    }
+}
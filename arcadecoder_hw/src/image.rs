@@ -0,0 +1,447 @@
+//! Converting full color images to the display
+//!
+//! An [`Image`] borrows a buffer of 24-bit pixels (3 bytes each, red then green then blue, in rows from the top-left)
+//! of any size. It is scaled to fill the 12x12 display by averaging the pixels that cover each display pixel, then each
+//! pixel is converted to one of the 8 [`Color`]s, using [`Dither`] to mix colors that the display cannot show.
+//!
+//! Conversion uses no allocation, so images can be converted on the device as well as on the host.
+//!
+//! ## Example
+//! ```
+//! use arcadecoder_hw::{image::{Dither, Image}, sprite::Sprite, BLACK, WHITE};
+//!
+//! // a 2x1 image, scaled so that each pixel covers half of the display
+//! let image = Image::new(2, 1, &[0x00, 0x00, 0x00, 0xff, 0xff, 0xff]).with_dither(Dither::Bayer);
+//!
+//! let fb = image.to_framebuffer();
+//! assert_eq!(fb.get_pixel((0, 0)), Some(BLACK));
+//! assert_eq!(fb.get_pixel((11, 11)), Some(WHITE));
+//!
+//! let pixels = image.to_sprite_pixels();
+//! let sprite = Sprite::new(12, 12, &pixels);
+//! assert_eq!(sprite.pixel(11, 0), Some(WHITE));
+//! ```
+
+use crate::framebuffer::{ColorGrid, Framebuffer, HEIGHT, WIDTH};
+use crate::Color;
+
+/// Number of bytes used for each pixel of an [`Image`].
+pub const BYTES_PER_PIXEL: usize = 3;
+
+/// Number of bytes of packed [`Sprite`](crate::sprite::Sprite) pixels for the whole display.
+pub const SPRITE_BYTES: usize = (WIDTH * HEIGHT * 3).div_ceil(8);
+
+/// Threshold map for [`Dither::Bayer`], with a value from 0 to 15 for each position in a 4x4 tile.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How colors that the display cannot show are converted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+    /// Use the nearest color for each pixel. This keeps pixel art and logos crisp.
+    #[default]
+    Nearest,
+
+    /// Mix colors in a fixed 4x4 pattern. This keeps a regular texture, so it does not flicker between frames of an
+    /// animation.
+    Bayer,
+
+    /// Spread the difference between each pixel and its nearest color over the pixels next to it. This gives the
+    /// most detail for photos.
+    FloydSteinberg,
+}
+
+/// A borrowed buffer of 24-bit pixels that can be converted for the display.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Image<'a> {
+    width: usize,
+    height: usize,
+    pixels: &'a [u8],
+    dither: Dither,
+}
+
+impl<'a> Image<'a> {
+    /// Create an image from its size and pixels. Pixels past the end of the buffer are black.
+    pub const fn new(width: usize, height: usize, pixels: &'a [u8]) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+            dither: Dither::Nearest,
+        }
+    }
+
+    /// Set how colors are converted.
+    pub const fn with_dither(mut self, dither: Dither) -> Self {
+        self.dither = dither;
+        self
+    }
+
+    /// Width of the image in pixels.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in pixels.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// How colors are converted.
+    pub const fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    /// Scale the image to the display and convert the color of every pixel.
+    pub fn to_colors(&self) -> ColorGrid {
+        let mut colors = [[Color::default(); WIDTH]; HEIGHT];
+
+        match self.dither {
+            Dither::Nearest => {
+                for (y, row) in colors.iter_mut().enumerate() {
+                    for (x, color) in row.iter_mut().enumerate() {
+                        let [r, g, b] = self.scaled_pixel(x, y);
+                        *color = Color::from_rgb888(r, g, b);
+                    }
+                }
+            }
+            Dither::Bayer => {
+                for (y, row) in colors.iter_mut().enumerate() {
+                    for (x, color) in row.iter_mut().enumerate() {
+                        // a channel is on if it is brighter than the threshold for this position, which is spread
+                        // evenly between 0 and 255 across the tile
+                        let threshold = (2 * u32::from(BAYER[y % 4][x % 4]) + 1) * 255;
+                        let [r, g, b] = self
+                            .scaled_pixel(x, y)
+                            .map(|v| u32::from(v) * 32 > threshold);
+                        *color = Color::from_rgb(r, g, b);
+                    }
+                }
+            }
+            Dither::FloydSteinberg => {
+                let mut values = [[[0i16; 3]; WIDTH]; HEIGHT];
+                for (y, row) in values.iter_mut().enumerate() {
+                    for (x, value) in row.iter_mut().enumerate() {
+                        *value = self.scaled_pixel(x, y).map(i16::from);
+                    }
+                }
+
+                for y in 0..HEIGHT {
+                    for x in 0..WIDTH {
+                        let value = values[y][x];
+                        let on = value.map(|v| v >= 0x80);
+                        colors[y][x] = Color::from_rgb(on[0], on[1], on[2]);
+
+                        for channel in 0..3 {
+                            let error = value[channel] - if on[channel] { 0xff } else { 0 };
+                            let mut spread = |x: Option<usize>, y: usize, weight: i16| {
+                                if let Some(v) = x
+                                    .filter(|x| *x < WIDTH && y < HEIGHT)
+                                    .map(|x| &mut values[y][x][channel])
+                                {
+                                    // errors can build up, so keep values within a range that cannot overflow
+                                    *v = (*v + error * weight / 16).clamp(-0xff, 0x1ff);
+                                }
+                            };
+                            spread(Some(x + 1), y, 7);
+                            spread(x.checked_sub(1), y + 1, 3);
+                            spread(Some(x), y + 1, 5);
+                            spread(Some(x + 1), y + 1, 1);
+                        }
+                    }
+                }
+            }
+        }
+
+        colors
+    }
+
+    /// Scale the image to the display and convert it to a framebuffer.
+    pub fn to_framebuffer(&self) -> Framebuffer {
+        Framebuffer::from_colors(&self.to_colors())
+    }
+
+    /// Scale the image to the display and convert it to packed pixels for a 12x12
+    /// [`Sprite`](crate::sprite::Sprite).
+    pub fn to_sprite_pixels(&self) -> [u8; SPRITE_BYTES] {
+        let mut pixels = [0; SPRITE_BYTES];
+        let colors = self.to_colors();
+
+        for (i, color) in colors.iter().flatten().enumerate() {
+            for (channel, on) in [color.red(), color.green(), color.blue()]
+                .into_iter()
+                .enumerate()
+            {
+                let index = i * 3 + channel;
+                if on {
+                    pixels[index / 8] |= 0x80 >> (index % 8);
+                }
+            }
+        }
+
+        pixels
+    }
+
+    /// Get the average color of the part of the image covered by a display pixel.
+    fn scaled_pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let (xs, ys) = (span(x, self.width, WIDTH), span(y, self.height, HEIGHT));
+        if xs.is_empty() || ys.is_empty() {
+            return [0; 3];
+        }
+
+        let mut total = [0u64; 3];
+        for sy in ys.clone() {
+            for sx in xs.clone() {
+                let pixel = sy
+                    .checked_mul(self.width)
+                    .and_then(|i| i.checked_add(sx)?.checked_mul(BYTES_PER_PIXEL))
+                    .and_then(|start| self.pixels.get(start..start.checked_add(BYTES_PER_PIXEL)?))
+                    .unwrap_or(&[0; BYTES_PER_PIXEL]);
+                for (total, v) in total.iter_mut().zip(pixel) {
+                    *total += u64::from(*v);
+                }
+            }
+        }
+
+        // round to the nearest value
+        let count = (xs.len() * ys.len()) as u64;
+        total.map(|total| ((total + count / 2) / count) as u8)
+    }
+}
+
+/// Get the image pixels covered by a display pixel, on an axis that is `size` pixels in the image and `display`
+/// pixels on the display. Every display pixel covers at least one image pixel, unless the image is empty.
+fn span(pos: usize, size: usize, display: usize) -> core::ops::Range<usize> {
+    let scale = |pos: usize| (pos as u128 * size as u128 / display as u128) as usize;
+    let start = scale(pos);
+    let end = scale(pos + 1).max(start + 1).min(size);
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::Sprite;
+    use crate::{BLACK, BLUE, GREEN, RED, WHITE, YELLOW};
+
+    /// A 12x1 gray gradient from black to white, one pixel for each column of the display.
+    const GRADIENT: [u8; WIDTH * BYTES_PER_PIXEL] = {
+        let mut pixels = [0; WIDTH * BYTES_PER_PIXEL];
+        let mut i = 0;
+        while i < pixels.len() {
+            pixels[i] = (i / BYTES_PER_PIXEL * 255 / (WIDTH - 1)) as u8;
+            i += 1;
+        }
+        pixels
+    };
+
+    /// Parse rows of `#` for white and `.` for black, or the first letter of another color.
+    fn grid(rows: [&str; HEIGHT]) -> ColorGrid {
+        rows.map(|row| {
+            let mut colors = row.bytes().map(|c| match c {
+                b'#' => WHITE,
+                b'R' => RED,
+                b'G' => GREEN,
+                b'B' => BLUE,
+                b'Y' => YELLOW,
+                _ => BLACK,
+            });
+            core::array::from_fn(|_| colors.next().unwrap())
+        })
+    }
+
+    /// Repeat rows down the display.
+    fn tiled(rows: &[&str]) -> ColorGrid {
+        grid(core::array::from_fn(|y| rows[y % rows.len()]))
+    }
+
+    #[test]
+    fn nearest_on_gradient() {
+        // the columns are 0, 23, 46, 69, 92, 115, 139, ...
+        let colors = Image::new(WIDTH, 1, &GRADIENT).to_colors();
+        assert_eq!(colors, grid(["......######"; HEIGHT]));
+    }
+
+    #[test]
+    fn solid_colors_are_not_dithered() {
+        for dither in [Dither::Nearest, Dither::Bayer, Dither::FloydSteinberg] {
+            for (pixel, color) in [
+                ([0; BYTES_PER_PIXEL], BLACK),
+                ([255; BYTES_PER_PIXEL], WHITE),
+                ([255, 255, 0], YELLOW),
+            ] {
+                let colors = Image::new(1, 1, &pixel).with_dither(dither).to_colors();
+                assert_eq!(colors, [[color; WIDTH]; HEIGHT], "{dither:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn bayer_on_gray() {
+        // half brightness is a checkerboard
+        let checkerboard = tiled(&["#.#.#.#.#.#.", ".#.#.#.#.#.#"]);
+        let image = Image::new(1, 1, &[128; BYTES_PER_PIXEL]).with_dither(Dither::Bayer);
+        assert_eq!(image.to_colors(), checkerboard);
+
+        // quarter brightness is every other pixel of every other row
+        let image = Image::new(1, 1, &[64; BYTES_PER_PIXEL]).with_dither(Dither::Bayer);
+        assert_eq!(image.to_colors(), tiled(&["#.#.#.#.#.#.", "............"]));
+
+        // each channel is dithered on its own
+        let image = Image::new(1, 1, &[128, 0, 0]).with_dither(Dither::Bayer);
+        assert_eq!(image.to_colors(), tiled(&["R.R.R.R.R.R.", ".R.R.R.R.R.R"]));
+    }
+
+    #[test]
+    fn bayer_on_gradient() {
+        // the pattern repeats every 4 rows, and gets brighter to the right
+        let image = Image::new(WIDTH, 1, &GRADIENT).with_dither(Dither::Bayer);
+        let tile = [
+            "..#.#.#.####",
+            ".....#.#.#.#",
+            "..#.#.######",
+            ".......#.###",
+        ];
+        assert_eq!(image.to_colors(), tiled(&tile));
+    }
+
+    #[test]
+    fn floyd_steinberg_on_gray() {
+        let image = Image::new(1, 1, &[128; BYTES_PER_PIXEL]).with_dither(Dither::FloydSteinberg);
+        assert_eq!(image.to_colors(), tiled(&["#.#.#.#.#.#.", ".#.#.#.#.#.#"]));
+    }
+
+    #[test]
+    fn floyd_steinberg_on_gradient() {
+        let image = Image::new(WIDTH, 1, &GRADIENT).with_dither(Dither::FloydSteinberg);
+        let colors = image.to_colors();
+        assert_eq!(
+            colors,
+            grid([
+                "....#.#.####",
+                ".....#.#####",
+                "...#.#.#.###",
+                "....#.######",
+                "..#..#.#.###",
+                "....#.#.####",
+                "....#.##.###",
+                "...#..#.####",
+                "....#.##.###",
+                "...#.#.#####",
+                ".....#.#.###",
+                "..#.#.#.####",
+            ])
+        );
+
+        // about as many pixels are lit in each column as the brightness of the gradient there
+        for x in 0..WIDTH {
+            let lit = colors.iter().filter(|row| row[x] == WHITE).count();
+            let expected = usize::from(GRADIENT[x * BYTES_PER_PIXEL]) * HEIGHT / 255;
+            assert!(lit.abs_diff(expected) <= 3, "column {x}: {lit} lit");
+        }
+    }
+
+    #[test]
+    fn scale_up() {
+        // each pixel of a 3x3 image covers 4x4 pixels of the display
+        let mut pixels = [0; 9 * BYTES_PER_PIXEL];
+        pixels[4 * BYTES_PER_PIXEL..][..BYTES_PER_PIXEL].copy_from_slice(&[255, 0, 0]);
+        pixels[8 * BYTES_PER_PIXEL..][..BYTES_PER_PIXEL].copy_from_slice(&[0, 0, 255]);
+
+        let rows = [
+            "............",
+            "............",
+            "............",
+            "............",
+            "....RRRR....",
+            "....RRRR....",
+            "....RRRR....",
+            "....RRRR....",
+            "........BBBB",
+            "........BBBB",
+            "........BBBB",
+            "........BBBB",
+        ];
+        assert_eq!(Image::new(3, 3, &pixels).to_colors(), grid(rows));
+    }
+
+    #[test]
+    fn scale_up_uneven() {
+        // 5 columns do not divide the display evenly, so some cover 2 pixels and some 3
+        let pixels = [
+            [255, 0, 0],
+            [0, 255, 0],
+            [0, 0, 255],
+            [255, 255, 255],
+            [255, 255, 0],
+        ];
+        let image = Image::new(5, 1, pixels.as_flattened());
+        assert_eq!(image.to_colors(), grid(["RRRGGBBB##YY"; HEIGHT]));
+    }
+
+    #[test]
+    fn scale_down() {
+        // each display pixel covers a 2x2 block of a 24x24 image and is the average of it
+        let mut pixels = [0; 24 * 24 * BYTES_PER_PIXEL];
+        for (i, pixel) in pixels.chunks_exact_mut(BYTES_PER_PIXEL).enumerate() {
+            let (x, y) = (i % 24, i / 24);
+            // the left display column has half of each block lit, which averages to 128
+            let lit = if x < 2 {
+                y % 2 == 0
+            } else {
+                x % 2 == 0 && y % 2 == 0
+            };
+            if lit {
+                pixel.fill(255);
+            }
+        }
+
+        // a quarter of each other block is lit, which averages to 64
+        let colors = Image::new(24, 24, &pixels).to_colors();
+        assert_eq!(colors, grid(["#..........."; HEIGHT]));
+    }
+
+    #[test]
+    fn scale_down_uneven() {
+        // 13 columns, so one display pixel averages 2 image pixels
+        let mut pixels = [0; 13 * BYTES_PER_PIXEL];
+        pixels[..2 * BYTES_PER_PIXEL].fill(255);
+        pixels[12 * BYTES_PER_PIXEL..].fill(255);
+        let colors = Image::new(13, 1, &pixels).to_colors();
+        assert_eq!(colors, grid(["##.........#"; HEIGHT]));
+    }
+
+    #[test]
+    fn missing_pixels_are_black() {
+        assert_eq!(Image::new(0, 0, &[]).to_colors(), [[BLACK; WIDTH]; HEIGHT]);
+        assert_eq!(
+            Image::new(5, 0, &[255; 15]).to_colors(),
+            [[BLACK; WIDTH]; HEIGHT]
+        );
+
+        // only the first pixel of a 2x2 image is in the buffer
+        let colors = Image::new(2, 2, &[255; BYTES_PER_PIXEL]).to_colors();
+        let rows = core::array::from_fn(|y| {
+            if y < 6 {
+                "######......"
+            } else {
+                "............"
+            }
+        });
+        assert_eq!(colors, grid(rows));
+    }
+
+    #[test]
+    fn sprite_pixels_match_colors() {
+        let image = Image::new(WIDTH, 1, &GRADIENT).with_dither(Dither::FloydSteinberg);
+        let colors = image.to_colors();
+        let pixels = image.to_sprite_pixels();
+        let sprite = Sprite::new(WIDTH as u8, HEIGHT as u8, &pixels);
+
+        for (y, row) in colors.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                assert_eq!(sprite.pixel(x, y), Some(*color));
+            }
+        }
+        assert_eq!(Framebuffer::from_colors(&colors), image.to_framebuffer());
+    }
+}
@@ -0,0 +1,314 @@
+//! Scrolling text for strings that are too long for the display
+//!
+//! Only two 5x5 characters fit across the display, so longer text like team names or messages has to scroll. A
+//! [`Marquee`] moves its text through an area at a set speed, and is driven by the time elapsed since it was last
+//! updated so the speed does not depend on how often the display is redrawn. The position is tracked to a fraction of a
+//! pixel, so slow speeds scroll smoothly.
+//!
+//! ## Example
+//! ```
+//! use arcadecoder_hw::{
+//!     font::FONT_5X5,
+//!     framebuffer::Framebuffer,
+//!     geometry::Rect,
+//!     marquee::{Marquee, ScrollMode},
+//!     WHITE,
+//! };
+//!
+//! let mut marquee = Marquee::new("HELLO", &FONT_5X5, Rect::new(0, 3, 12, 5))
+//!     .with_speed(20)
+//!     .with_mode(ScrollMode::OneShot);
+//!
+//! let mut fb = Framebuffer::new();
+//! while !marquee.is_finished() {
+//!     // 10ms since the last update
+//!     if marquee.update(10_000) {
+//!         fb.clear();
+//!         marquee.draw(&mut fb, WHITE);
+//!     }
+//! }
+//! ```
+
+use crate::font::Font;
+use crate::framebuffer::Framebuffer;
+use crate::geometry::Rect;
+use crate::Color;
+
+/// Sub-pixel steps in a pixel, so the distance moved in a microsecond at a speed in pixels per second is exact.
+const STEPS_PER_PIXEL: u64 = 1_000_000;
+
+/// How a [`Marquee`] moves its text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollMode {
+    /// Scroll in from the right and out to the left, then start again.
+    #[default]
+    Loop,
+
+    /// Scroll back and forth between showing the start and the end of the text. Text that fits in the area moves
+    /// between the left and right edges instead, and text exactly as wide as the area stays still.
+    Bounce,
+
+    /// Scroll in from the right and out to the left once, then finish.
+    OneShot,
+}
+
+/// Text that scrolls through an area of the display.
+#[derive(Clone, Copy, Debug)]
+pub struct Marquee<'a> {
+    text: &'a str,
+    font: Font<'a>,
+    area: Rect,
+    text_width: usize,
+    speed: u32,
+    mode: ScrollMode,
+    travelled: u64,
+}
+
+impl<'a> Marquee<'a> {
+    /// Create a marquee that scrolls `text` through `area`, with the top of the text at the top of the area.
+    pub fn new(text: &'a str, font: &Font<'a>, area: Rect) -> Self {
+        Self {
+            text,
+            font: *font,
+            area,
+            text_width: font.measure_text(text),
+            speed: 10,
+            mode: ScrollMode::Loop,
+            travelled: 0,
+        }
+    }
+
+    /// Set the speed in pixels per second.
+    pub const fn with_speed(mut self, pixels_per_second: u32) -> Self {
+        self.speed = pixels_per_second;
+        self
+    }
+
+    /// Set how the text moves.
+    pub const fn with_mode(mut self, mode: ScrollMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The speed in pixels per second.
+    pub const fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    /// How the text moves.
+    pub const fn mode(&self) -> ScrollMode {
+        self.mode
+    }
+
+    /// Move the text by the distance travelled in `elapsed_us` microseconds.
+    ///
+    /// Returns `true` if the text moved to a different pixel and needs to be redrawn.
+    pub fn update(&mut self, elapsed_us: u32) -> bool {
+        let previous = self.offset();
+        self.travelled = self
+            .travelled
+            .saturating_add(u64::from(self.speed) * u64::from(elapsed_us));
+
+        // keep the distance within one cycle so it never overflows
+        let cycle = self.distance() * STEPS_PER_PIXEL;
+        match self.mode {
+            ScrollMode::Loop if cycle > 0 => self.travelled %= cycle,
+            ScrollMode::Bounce if cycle > 0 => self.travelled %= 2 * cycle,
+            // text that exactly fits the area has nowhere to move
+            ScrollMode::Bounce => self.travelled = 0,
+            ScrollMode::OneShot => self.travelled = self.travelled.min(cycle),
+            _ => {}
+        }
+
+        self.offset() != previous
+    }
+
+    /// Whether a [`ScrollMode::OneShot`] marquee has scrolled all of its text out of the area. Other modes never
+    /// finish.
+    pub fn is_finished(&self) -> bool {
+        self.mode == ScrollMode::OneShot && self.travelled >= self.distance() * STEPS_PER_PIXEL
+    }
+
+    /// Start scrolling from the beginning again.
+    pub fn reset(&mut self) {
+        self.travelled = 0;
+    }
+
+    /// Position of the left edge of the text relative to the left of the area, in whole pixels.
+    pub fn offset(&self) -> i32 {
+        let (width, text_width) = (i64::from(self.area.width), self.text_width as i64);
+        let pixels = (self.travelled / STEPS_PER_PIXEL) as i64;
+
+        let offset = match self.mode {
+            ScrollMode::Loop | ScrollMode::OneShot => width - pixels,
+            ScrollMode::Bounce => {
+                // move away from the start and back again
+                let distance = self.distance() as i64;
+                let moved = if pixels > distance {
+                    2 * distance - pixels
+                } else {
+                    pixels
+                };
+
+                if text_width > width {
+                    -moved
+                } else {
+                    moved
+                }
+            }
+        };
+        i32::try_from(offset).unwrap_or(i32::MIN)
+    }
+
+    /// Draw the text at its current position, clipped to the area.
+    pub fn draw(&self, fb: &mut Framebuffer, color: Color) {
+        let start_pos = self.area.top_left().offset(self.offset(), 0);
+        fb.draw_text_at(self.text, &self.font, start_pos, self.area, color);
+    }
+
+    /// Number of pixels moved in one pass through the area.
+    fn distance(&self) -> u64 {
+        match self.mode {
+            ScrollMode::Loop | ScrollMode::OneShot => {
+                u64::from(self.area.width) + self.text_width as u64
+            }
+            ScrollMode::Bounce => u64::from(self.area.width).abs_diff(self.text_width as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::FONT_5X5;
+    use crate::text::Alignment;
+    use crate::WHITE;
+
+    /// Microseconds to move a pixel at the default speed.
+    const PIXEL_US: u32 = 100_000;
+
+    fn marquee(text: &'static str, mode: ScrollMode) -> Marquee<'static> {
+        Marquee::new(text, &FONT_5X5, Rect::new(0, 3, 12, 5)).with_mode(mode)
+    }
+
+    /// Move a marquee by a number of pixels, returning its offset after each one.
+    fn offsets<const N: usize>(marquee: &mut Marquee) -> [i32; N] {
+        core::array::from_fn(|_| {
+            assert!(marquee.update(PIXEL_US));
+            marquee.offset()
+        })
+    }
+
+    #[test]
+    fn loop_scrolls_through_and_starts_again() {
+        // "HELLO" is 29 pixels wide, so it takes 41 pixels to scroll through
+        let mut marquee = marquee("HELLO", ScrollMode::Loop);
+        assert_eq!(marquee.offset(), 12);
+
+        assert_eq!(offsets(&mut marquee), [11, 10, 9]);
+        assert!(marquee.update(37 * PIXEL_US));
+        assert_eq!(marquee.offset(), -28);
+        assert_eq!(offsets(&mut marquee), [12, 11]);
+        assert!(!marquee.is_finished());
+    }
+
+    #[test]
+    fn bounce_scrolls_between_start_and_end() {
+        // the text is 17 pixels wider than the area
+        let mut marquee = marquee("HELLO", ScrollMode::Bounce);
+        assert_eq!(marquee.offset(), 0);
+
+        assert!(marquee.update(16 * PIXEL_US));
+        assert_eq!(marquee.offset(), -16);
+        assert_eq!(offsets(&mut marquee), [-17, -16, -15]);
+        assert!(marquee.update(14 * PIXEL_US));
+        assert_eq!(marquee.offset(), -1);
+        assert_eq!(offsets(&mut marquee), [0, -1]);
+        assert!(!marquee.is_finished());
+    }
+
+    #[test]
+    fn bounce_moves_short_text_between_edges() {
+        // "1" is 5 pixels wide, so it moves 7 pixels to the right edge and back
+        let mut marquee = marquee("1", ScrollMode::Bounce);
+        assert_eq!(marquee.offset(), 0);
+
+        assert!(marquee.update(6 * PIXEL_US));
+        assert_eq!(offsets(&mut marquee), [7, 6]);
+        assert!(marquee.update(6 * PIXEL_US));
+        assert_eq!(marquee.offset(), 0);
+        assert_eq!(offsets(&mut marquee), [1]);
+    }
+
+    #[test]
+    fn bounce_keeps_text_that_fits_exactly_still() {
+        let font = FONT_5X5.with_letter_spacing(1);
+        assert_eq!(font.measure_text("11"), 12);
+        let mut marquee =
+            Marquee::new("11", &font, Rect::new(0, 3, 12, 5)).with_mode(ScrollMode::Bounce);
+        assert_eq!(marquee.offset(), 0);
+
+        for elapsed in [PIXEL_US, 7 * PIXEL_US, u32::MAX] {
+            assert!(!marquee.update(elapsed));
+            assert_eq!(marquee.offset(), 0);
+        }
+        assert!(!marquee.is_finished());
+    }
+
+    #[test]
+    fn one_shot_finishes() {
+        let mut marquee = marquee("HELLO", ScrollMode::OneShot);
+        assert_eq!(marquee.offset(), 12);
+
+        assert!(marquee.update(40 * PIXEL_US));
+        assert_eq!(marquee.offset(), -28);
+        assert!(!marquee.is_finished());
+
+        assert!(marquee.update(PIXEL_US));
+        assert_eq!(marquee.offset(), -29);
+        assert!(marquee.is_finished());
+
+        // it stays where it finished
+        assert!(!marquee.update(u32::MAX));
+        assert_eq!(marquee.offset(), -29);
+        assert!(marquee.is_finished());
+
+        marquee.reset();
+        assert_eq!(marquee.offset(), 12);
+        assert!(!marquee.is_finished());
+    }
+
+    #[test]
+    fn moves_by_fractions_of_a_pixel() {
+        let mut marquee = marquee("HELLO", ScrollMode::Loop).with_speed(3);
+
+        // 3 pixels a second is a pixel every 333,333.3 microseconds
+        assert!(!marquee.update(200_000));
+        assert!(!marquee.update(133_333));
+        assert_eq!(marquee.offset(), 12);
+        assert!(marquee.update(1));
+        assert_eq!(marquee.offset(), 11);
+
+        let mut stopped = marquee.with_speed(0);
+        assert!(!stopped.update(u32::MAX));
+        assert_eq!(stopped.offset(), 11);
+    }
+
+    #[test]
+    fn draws_clipped_to_area() {
+        let mut marquee = marquee("HELLO", ScrollMode::Loop);
+
+        // the text starts just outside the area
+        let mut fb = Framebuffer::new();
+        marquee.draw(&mut fb, WHITE);
+        assert_eq!(fb, Framebuffer::new());
+
+        assert!(marquee.update(14 * PIXEL_US));
+        let mut fb = Framebuffer::new();
+        marquee.draw(&mut fb, WHITE);
+
+        let mut expected = Framebuffer::new();
+        expected.draw_text("HELLO", &FONT_5X5, (-2, 3), Alignment::Left, WHITE);
+        assert_eq!(fb, expected);
+    }
+}
@@ -0,0 +1,227 @@
+//! Rotating and mirroring the whole panel
+//!
+//! Boards mounted sideways or upside down can set an [`Orientation`] with
+//! [`ArcadeCoderConfig::with_orientation`](crate::config::ArcadeCoderConfig::with_orientation). Drawing is then done
+//! in the application's coordinates and the driver transforms each frame to the native layout when it is shown, while
+//! [`ButtonEvent`](crate::ButtonEvent)s are transformed back so they line up with what is drawn.
+
+use crate::framebuffer::{HEIGHT, WIDTH};
+use crate::Coordinates;
+
+/// Clockwise rotation of the image on the panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// How the application's coordinates map on to the panel.
+///
+/// Mirroring is applied to the image before it is rotated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Orientation {
+    /// Clockwise rotation of the image.
+    pub rotation: Rotation,
+
+    /// Flip the image left to right.
+    pub mirror_horizontal: bool,
+
+    /// Flip the image top to bottom.
+    pub mirror_vertical: bool,
+}
+
+impl Orientation {
+    /// The panel's own orientation, with (0, 0) at the top-left when the board is upright.
+    pub const NATIVE: Self = Self::new(Rotation::Deg0);
+
+    /// Create an orientation with a rotation and no mirroring.
+    pub const fn new(rotation: Rotation) -> Self {
+        Self {
+            rotation,
+            mirror_horizontal: false,
+            mirror_vertical: false,
+        }
+    }
+
+    /// Set whether the image is flipped left to right.
+    pub const fn with_mirror_horizontal(mut self, mirror: bool) -> Self {
+        self.mirror_horizontal = mirror;
+        self
+    }
+
+    /// Set whether the image is flipped top to bottom.
+    pub const fn with_mirror_vertical(mut self, mirror: bool) -> Self {
+        self.mirror_vertical = mirror;
+        self
+    }
+
+    /// Convert coordinates in this orientation to the panel's native coordinates.
+    ///
+    /// The coordinates must be on the display.
+    pub fn to_native(self, pos: Coordinates) -> Coordinates {
+        let (mut x, mut y) = pos;
+        if self.mirror_horizontal {
+            x = WIDTH - 1 - x;
+        }
+        if self.mirror_vertical {
+            y = HEIGHT - 1 - y;
+        }
+
+        match self.rotation {
+            Rotation::Deg0 => (x, y),
+            Rotation::Deg90 => (WIDTH - 1 - y, x),
+            Rotation::Deg180 => (WIDTH - 1 - x, HEIGHT - 1 - y),
+            Rotation::Deg270 => (y, HEIGHT - 1 - x),
+        }
+    }
+
+    /// Convert the panel's native coordinates to coordinates in this orientation.
+    ///
+    /// The coordinates must be on the display.
+    pub fn to_oriented(self, pos: Coordinates) -> Coordinates {
+        let (nx, ny) = pos;
+        let (mut x, mut y) = match self.rotation {
+            Rotation::Deg0 => (nx, ny),
+            Rotation::Deg90 => (ny, WIDTH - 1 - nx),
+            Rotation::Deg180 => (WIDTH - 1 - nx, HEIGHT - 1 - ny),
+            Rotation::Deg270 => (HEIGHT - 1 - ny, nx),
+        };
+
+        if self.mirror_horizontal {
+            x = WIDTH - 1 - x;
+        }
+        if self.mirror_vertical {
+            y = HEIGHT - 1 - y;
+        }
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ArcadeCoderConfig;
+    use crate::driver::tests::Expected;
+    use crate::framebuffer::Framebuffer;
+    use crate::{ButtonEvent, BLACK, RED};
+
+    /// Every combination of rotation and mirroring.
+    fn orientations() -> impl Iterator<Item = Orientation> {
+        [
+            Rotation::Deg0,
+            Rotation::Deg90,
+            Rotation::Deg180,
+            Rotation::Deg270,
+        ]
+        .into_iter()
+        .flat_map(|rotation| {
+            [(false, false), (true, false), (false, true), (true, true)].map(|(h, v)| {
+                Orientation::new(rotation)
+                    .with_mirror_horizontal(h)
+                    .with_mirror_vertical(v)
+            })
+        })
+    }
+
+    fn positions() -> impl Iterator<Item = Coordinates> {
+        (0..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+    }
+
+    #[test]
+    fn coordinates_round_trip() {
+        assert_eq!(orientations().count(), 16);
+
+        for orientation in orientations() {
+            let mut seen = [[false; WIDTH]; HEIGHT];
+            for pos in positions() {
+                let native = orientation.to_native(pos);
+                assert_eq!(orientation.to_oriented(native), pos, "{orientation:?}");
+                assert_eq!(
+                    orientation.to_native(orientation.to_oriented(pos)),
+                    pos,
+                    "{orientation:?}"
+                );
+
+                // every pixel maps to a different native pixel
+                assert!(!seen[native.1][native.0], "{orientation:?}");
+                seen[native.1][native.0] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn top_left_moves_to_the_expected_corner() {
+        let corner = |orientation: Orientation| orientation.to_native((0, 0));
+
+        assert_eq!(corner(Orientation::NATIVE), (0, 0));
+        assert_eq!(corner(Orientation::new(Rotation::Deg90)), (11, 0));
+        assert_eq!(corner(Orientation::new(Rotation::Deg180)), (11, 11));
+        assert_eq!(corner(Orientation::new(Rotation::Deg270)), (0, 11));
+
+        let mirrored = Orientation::NATIVE.with_mirror_horizontal(true);
+        assert_eq!(corner(mirrored), (11, 0));
+        assert_eq!(corner(mirrored.with_mirror_vertical(true)), (11, 11));
+
+        // mirroring is applied before rotating
+        assert_eq!(
+            corner(Orientation::new(Rotation::Deg90).with_mirror_horizontal(true)),
+            (11, 11)
+        );
+        assert_eq!(
+            corner(Orientation::new(Rotation::Deg90).with_mirror_vertical(true)),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn framebuffer_is_drawn_in_native_layout() {
+        for orientation in orientations() {
+            let mut fb = Framebuffer::new();
+            fb.set_pixel((2, 9), RED);
+
+            let native = fb.oriented(orientation);
+            for pos in positions() {
+                let expected = if pos == orientation.to_native((2, 9)) {
+                    RED
+                } else {
+                    BLACK
+                };
+                assert_eq!(native.get_pixel(pos), Some(expected), "{orientation:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn button_events_line_up_with_drawing() {
+        for orientation in orientations() {
+            let (mut ac, mocks) = Expected::new().build();
+            let config = ArcadeCoderConfig::arcade_coder()
+                .with_debounce_reads(1)
+                .with_orientation(orientation);
+            ac.apply_config(&config).unwrap();
+
+            // press the button under native pixel (1, 10), which registers on the second read
+            ac.button_presses[10][1] = true;
+            let mut event = None;
+            ac.handle_input_events(|e| event = Some(e));
+            ac.handle_input_events(|e| event = Some(e));
+            let Some(ButtonEvent::Pressed(x, y)) = event else {
+                panic!("no press for {orientation:?}");
+            };
+
+            // drawing at the event's position lights the pressed button
+            let mut fb = Framebuffer::new();
+            fb.set_pixel((usize::from(x), usize::from(y)), RED);
+            assert_eq!(
+                fb.oriented(orientation).get_pixel((1, 10)),
+                Some(RED),
+                "{orientation:?}"
+            );
+
+            mocks.done();
+        }
+    }
+}
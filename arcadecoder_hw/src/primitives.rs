@@ -0,0 +1,502 @@
+//! Lines, shapes and flood fill
+//!
+//! These are drawn straight on to a [`Framebuffer`]. Shapes can be partly or completely off the display, and only the
+//! pixels that are on it are drawn. Outlines are the pixels of the filled shape on its edge, so an outline always lines
+//! up with the filled version of the same shape.
+//!
+//! ## Example
+//! ```
+//! use arcadecoder_hw::{framebuffer::Framebuffer, BLUE, RED, WHITE, YELLOW};
+//!
+//! let mut fb = Framebuffer::new();
+//! fb.draw_rect_outline((0, 0), (11, 11), WHITE);
+//! fb.draw_line((1, 1), (10, 10), RED);
+//! fb.fill_circle((6, 6), 3, YELLOW);
+//! fb.flood_fill((10, 2), BLUE);
+//! ```
+
+use core::ops::RangeInclusive;
+
+use crate::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::geometry::Point;
+use crate::Color;
+
+/// Largest radius used for circles and ellipses, which keeps the maths from overflowing. Anything larger is much bigger
+/// than the display anyway.
+const MAX_RADIUS: u32 = 1 << 30;
+
+impl Framebuffer {
+    /// Draw a line between two points (inclusive).
+    ///
+    /// Each column (or row, for steep lines) gets the pixel closest to the line, like Bresenham's algorithm. Only the
+    /// part of the line that is on the display is visited, so long lines far off the display are still quick.
+    pub fn draw_line(&mut self, start: impl Into<Point>, end: impl Into<Point>, color: Color) {
+        let (start, end) = (start.into(), end.into());
+        let (dx, dy) = (end.x.abs_diff(start.x), end.y.abs_diff(start.y));
+
+        if dx >= dy {
+            for x in on_display(start.x, end.x, WIDTH) {
+                let y = interpolate((start.x, start.y), (end.x, end.y), x);
+                self.set_pixel((x, y), color);
+            }
+        } else {
+            for y in on_display(start.y, end.y, HEIGHT) {
+                let x = interpolate((start.y, start.x), (end.y, end.x), y);
+                self.set_pixel((x, y), color);
+            }
+        }
+    }
+
+    /// Draw lines joining each point to the next.
+    pub fn draw_polyline<P: Into<Point> + Copy>(&mut self, points: &[P], color: Color) {
+        if let [point] = points {
+            self.set_pixel(*point, color);
+        }
+        for line in points.windows(2) {
+            self.draw_line(line[0], line[1], color);
+        }
+    }
+
+    /// Draw the outline of a rectangle between two opposite corners (inclusive), in any order.
+    pub fn draw_rect_outline(
+        &mut self,
+        pos1: impl Into<Point>,
+        pos2: impl Into<Point>,
+        color: Color,
+    ) {
+        let (pos1, pos2) = (pos1.into(), pos2.into());
+        let (left, top) = (pos1.x.min(pos2.x), pos1.y.min(pos2.y));
+        let (right, bottom) = (pos1.x.max(pos2.x), pos1.y.max(pos2.y));
+
+        self.draw_line((left, top), (right, top), color);
+        self.draw_line((left, bottom), (right, bottom), color);
+        self.draw_line((left, top), (left, bottom), color);
+        self.draw_line((right, top), (right, bottom), color);
+    }
+
+    /// Draw the outline of a circle.
+    pub fn draw_circle(&mut self, center: impl Into<Point>, radius: u32, color: Color) {
+        self.draw_ellipse(center, (radius, radius), color);
+    }
+
+    /// Draw a filled circle.
+    pub fn fill_circle(&mut self, center: impl Into<Point>, radius: u32, color: Color) {
+        self.fill_ellipse(center, (radius, radius), color);
+    }
+
+    /// Draw the outline of an ellipse with a horizontal and vertical radius.
+    pub fn draw_ellipse(&mut self, center: impl Into<Point>, radii: (u32, u32), color: Color) {
+        let center = center.into();
+        self.draw_outline(|pos| in_ellipse(center, radii, pos), color);
+    }
+
+    /// Draw a filled ellipse with a horizontal and vertical radius.
+    pub fn fill_ellipse(&mut self, center: impl Into<Point>, radii: (u32, u32), color: Color) {
+        let center = center.into();
+        self.draw_filled(|pos| in_ellipse(center, radii, pos), color);
+    }
+
+    /// Draw the outline of a triangle.
+    pub fn draw_triangle(
+        &mut self,
+        a: impl Into<Point>,
+        b: impl Into<Point>,
+        c: impl Into<Point>,
+        color: Color,
+    ) {
+        let (a, b, c) = (a.into(), b.into(), c.into());
+        self.draw_polyline(&[a, b, c, a], color);
+    }
+
+    /// Draw a filled triangle.
+    pub fn fill_triangle(
+        &mut self,
+        a: impl Into<Point>,
+        b: impl Into<Point>,
+        c: impl Into<Point>,
+        color: Color,
+    ) {
+        let (a, b, c) = (a.into(), b.into(), c.into());
+
+        // a triangle with no area is only its outline
+        if edge(a, b, c) != 0 {
+            self.draw_filled(
+                |p| {
+                    let w = [edge(b, c, p), edge(c, a, p), edge(a, b, p)];
+                    w.iter().all(|w| *w >= 0) || w.iter().all(|w| *w <= 0)
+                },
+                color,
+            );
+        }
+
+        // the edges make sure thin triangles match their outline
+        self.draw_triangle(a, b, c, color);
+    }
+
+    /// Fill the area around a pixel that is the same color as it, stopping at any other color.
+    ///
+    /// Only pixels directly above, below, left or right of each other are counted as touching.
+    pub fn flood_fill(&mut self, pos: impl Into<Point>, color: Color) {
+        let Some(pos) = pos.into().to_coordinates() else {
+            return;
+        };
+        let Some(target) = self.get_pixel(pos) else {
+            return;
+        };
+        if target == color {
+            return;
+        }
+
+        // every pixel is filled before it is pushed, so it can only be pushed once
+        let mut stack = [(0, 0); WIDTH * HEIGHT];
+        let mut len = 1;
+        stack[0] = pos;
+        self.set_pixel(pos, color);
+
+        while len > 0 {
+            len -= 1;
+            let (x, y) = stack[len];
+
+            // going off the top or left wraps around to a position that is off the display
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for neighbour in neighbours {
+                if self.get_pixel(neighbour) == Some(target) {
+                    self.set_pixel(neighbour, color);
+                    stack[len] = neighbour;
+                    len += 1;
+                }
+            }
+        }
+    }
+
+    /// Draw every pixel on the display that is inside a shape.
+    fn draw_filled(&mut self, inside: impl Fn(Point) -> bool, color: Color) {
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                if inside(Point::from((x, y))) {
+                    self.set_pixel((x, y), color);
+                }
+            }
+        }
+    }
+
+    /// Draw every pixel on the display that is inside a shape, but next to a pixel that is outside it.
+    fn draw_outline(&mut self, inside: impl Fn(Point) -> bool, color: Color) {
+        self.draw_filled(
+            |p| {
+                // pixels off the display are still checked, so shapes that are cut off do not get an edge there
+                inside(p)
+                    && [
+                        p.offset(-1, 0),
+                        p.offset(1, 0),
+                        p.offset(0, -1),
+                        p.offset(0, 1),
+                    ]
+                    .into_iter()
+                    .any(|p| !inside(p))
+            },
+            color,
+        );
+    }
+}
+
+/// Get the values between `a` and `b` (inclusive) that are on a display `size` pixels across.
+fn on_display(a: i32, b: i32, size: usize) -> RangeInclusive<i32> {
+    a.min(b).max(0)..=a.max(b).min(size as i32 - 1)
+}
+
+/// Get the value at `t` on the line through `(t0, v0)` and `(t1, v1)`, rounded to the nearest pixel.
+fn interpolate((t0, v0): (i32, i32), (t1, v1): (i32, i32), t: i32) -> i32 {
+    if t0 == t1 {
+        return v0;
+    }
+
+    // round half up, so the result is the same whichever end the line is drawn from
+    let (num, den) = (
+        (i128::from(t) - i128::from(t0)) * (i128::from(v1) - i128::from(v0)),
+        i128::from(t1) - i128::from(t0),
+    );
+    let (num, den) = if den < 0 { (-num, -den) } else { (num, den) };
+    let offset = (2 * num + den).div_euclid(2 * den);
+
+    // t is between t0 and t1, so the result is between v0 and v1
+    (i128::from(v0) + offset) as i32
+}
+
+/// Whether a point is inside an ellipse, including pixels that are at least half inside.
+fn in_ellipse(center: Point, radii: (u32, u32), pos: Point) -> bool {
+    let (dx, dy) = (pos.x.abs_diff(center.x), pos.y.abs_diff(center.y));
+    let (rx, ry) = (radii.0.min(MAX_RADIUS), radii.1.min(MAX_RADIUS));
+    if dx > rx || dy > ry {
+        return false;
+    }
+
+    // (dx / (rx + 0.5))^2 + (dy / (ry + 0.5))^2 <= 1, scaled up to whole numbers
+    let (dx, dy) = (2 * i128::from(dx), 2 * i128::from(dy));
+    let (rx, ry) = (2 * i128::from(rx) + 1, 2 * i128::from(ry) + 1);
+    dx * dx * ry * ry + dy * dy * rx * rx <= rx * rx * ry * ry
+}
+
+/// Which side of the line from `a` to `b` a point is on, or 0 if it is on the line.
+fn edge(a: Point, b: Point, p: Point) -> i128 {
+    let (ax, ay) = (i128::from(a.x), i128::from(a.y));
+    (i128::from(b.x) - ax) * (i128::from(p.y) - ay)
+        - (i128::from(b.y) - ay) * (i128::from(p.x) - ax)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BLUE, RED, WHITE};
+
+    /// Draw a framebuffer with only the given pixels lit.
+    fn with_pixels(points: &[(i32, i32)], color: Color) -> Framebuffer {
+        let mut fb = Framebuffer::new();
+        for point in points {
+            fb.set_pixel(*point, color);
+        }
+        fb
+    }
+
+    fn count(fb: &Framebuffer, color: Color) -> usize {
+        fb.pixels().filter(|(_, c)| *c == color).count()
+    }
+
+    #[test]
+    fn line_off_display() {
+        let mut fb = Framebuffer::new();
+        fb.draw_line((-5, -1), (-1, -8), WHITE);
+        fb.draw_line((12, 0), (20, 11), WHITE);
+        fb.draw_line((0, -3), (11, -3), WHITE);
+        fb.draw_line((3, 12), (3, 40), WHITE);
+        // passes the corner without touching the display
+        fb.draw_line((-2, 1), (1, -2), WHITE);
+        assert_eq!(fb, Framebuffer::new());
+    }
+
+    #[test]
+    fn line_partly_off_display() {
+        let mut fb = Framebuffer::new();
+        fb.draw_line((-3, 2), (14, 2), WHITE);
+        let row: [(i32, i32); 12] = core::array::from_fn(|x| (x as i32, 2));
+        assert_eq!(fb, with_pixels(&row, WHITE));
+
+        let mut fb = Framebuffer::new();
+        fb.draw_line((5, -10), (5, 4), WHITE);
+        assert_eq!(
+            fb,
+            with_pixels(&[(5, 0), (5, 1), (5, 2), (5, 3), (5, 4)], WHITE)
+        );
+
+        let mut fb = Framebuffer::new();
+        fb.draw_line((-2, -2), (3, 3), WHITE);
+        assert_eq!(fb, with_pixels(&[(0, 0), (1, 1), (2, 2), (3, 3)], WHITE));
+
+        // the visible part keeps the slope of the whole line, whichever end it is drawn from
+        let expected = with_pixels(
+            &[
+                (0, 1),
+                (1, 2),
+                (2, 2),
+                (3, 2),
+                (4, 3),
+                (5, 3),
+                (6, 3),
+                (7, 4),
+                (8, 4),
+                (9, 4),
+                (10, 5),
+                (11, 5),
+            ],
+            WHITE,
+        );
+        for (start, end) in [((-4, 0), (11, 5)), ((11, 5), (-4, 0))] {
+            let mut fb = Framebuffer::new();
+            fb.draw_line(start, end, WHITE);
+            assert_eq!(fb, expected);
+        }
+    }
+
+    #[test]
+    fn line_far_off_display() {
+        let mut fb = Framebuffer::new();
+        fb.draw_line((i32::MIN, 0), (i32::MAX, 0), WHITE);
+        fb.draw_line((0, i32::MIN), (0, i32::MAX), WHITE);
+        assert_eq!(count(&fb, WHITE), 23);
+        assert!((0..12).all(|i| fb.get_pixel((i, 0)) == Some(WHITE)));
+        assert!((0..12).all(|i| fb.get_pixel((0, i)) == Some(WHITE)));
+    }
+
+    #[test]
+    fn rect_corners_in_any_order() {
+        let mut expected = Framebuffer::new();
+        expected.draw_rect_outline((2, 3), (9, 8), WHITE);
+        assert_eq!(count(&expected, WHITE), 24);
+        assert_eq!(expected.get_pixel((2, 3)), Some(WHITE));
+        assert_eq!(expected.get_pixel((9, 8)), Some(WHITE));
+        assert_eq!(expected.get_pixel((5, 5)), Some(crate::BLACK));
+
+        for (pos1, pos2) in [((9, 8), (2, 3)), ((2, 8), (9, 3)), ((9, 3), (2, 8))] {
+            let mut fb = Framebuffer::new();
+            fb.draw_rect_outline(pos1, pos2, WHITE);
+            assert_eq!(fb, expected);
+        }
+    }
+
+    #[test]
+    fn rect_partly_off_display() {
+        // only the right and bottom edges are on the display
+        let mut fb = Framebuffer::new();
+        fb.draw_rect_outline((5, 5), (-2, -2), WHITE);
+
+        let mut expected = Framebuffer::new();
+        expected.draw_line((0, 5), (5, 5), WHITE);
+        expected.draw_line((5, 0), (5, 5), WHITE);
+        assert_eq!(fb, expected);
+    }
+
+    #[test]
+    fn rect_with_no_area() {
+        let mut fb = Framebuffer::new();
+        fb.draw_rect_outline((4, 4), (4, 4), WHITE);
+        assert_eq!(fb, with_pixels(&[(4, 4)], WHITE));
+
+        let mut fb = Framebuffer::new();
+        fb.draw_rect_outline((1, 6), (3, 6), WHITE);
+        assert_eq!(fb, with_pixels(&[(1, 6), (2, 6), (3, 6)], WHITE));
+    }
+
+    #[test]
+    fn fill_triangle() {
+        // the pixels with x + y <= 3
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle((0, 0), (3, 0), (0, 3), WHITE);
+        assert_eq!(count(&fb, WHITE), 10);
+        assert!(fb
+            .pixels()
+            .all(|((x, y), color)| (color == WHITE) == (x + y <= 3)));
+
+        // the winding does not matter
+        let mut reversed = Framebuffer::new();
+        reversed.fill_triangle((0, 0), (0, 3), (3, 0), WHITE);
+        assert_eq!(reversed, fb);
+    }
+
+    #[test]
+    fn degenerate_triangles() {
+        // every point on a line
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle((1, 1), (5, 5), (9, 9), WHITE);
+        let mut line = Framebuffer::new();
+        line.draw_line((1, 1), (9, 9), WHITE);
+        assert_eq!(fb, line);
+
+        // two points the same
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle((2, 2), (2, 2), (8, 2), WHITE);
+        let mut line = Framebuffer::new();
+        line.draw_line((2, 2), (8, 2), WHITE);
+        assert_eq!(fb, line);
+
+        // every point the same
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle((4, 4), (4, 4), (4, 4), WHITE);
+        fb.draw_triangle((7, 7), (7, 7), (7, 7), WHITE);
+        assert_eq!(fb, with_pixels(&[(4, 4), (7, 7)], WHITE));
+
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle((-3, -3), (-3, -3), (-3, -3), WHITE);
+        assert_eq!(fb, Framebuffer::new());
+    }
+
+    #[test]
+    fn triangle_with_extreme_coordinates() {
+        // covers the whole display
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle(
+            (i32::MIN, i32::MIN),
+            (i32::MAX, i32::MIN),
+            (0, i32::MAX),
+            WHITE,
+        );
+        assert_eq!(count(&fb, WHITE), 144);
+
+        // ends just left of the display
+        let mut fb = Framebuffer::new();
+        fb.fill_triangle((i32::MIN, i32::MIN), (-1, 0), (i32::MIN, i32::MAX), WHITE);
+        assert_eq!(fb, Framebuffer::new());
+    }
+
+    #[test]
+    fn flood_fill_from_corners_and_edges() {
+        for pos in [
+            (0, 0),
+            (11, 0),
+            (0, 11),
+            (11, 11),
+            (6, 0),
+            (0, 6),
+            (11, 6),
+            (6, 11),
+        ] {
+            let mut fb = Framebuffer::new();
+            fb.flood_fill(pos, BLUE);
+            assert_eq!(count(&fb, BLUE), WIDTH * HEIGHT, "{pos:?}");
+        }
+    }
+
+    #[test]
+    fn flood_fill_stops_at_other_colors() {
+        let mut wall = Framebuffer::new();
+        wall.draw_line((3, 0), (3, 11), RED);
+
+        // the left of the wall
+        let mut fb = wall;
+        fb.flood_fill((0, 0), BLUE);
+        assert_eq!(count(&fb, BLUE), 36);
+        assert!(fb
+            .pixels()
+            .all(|((x, _), color)| (color == BLUE) == (x < 3)));
+
+        // the right of the wall, starting on the edge
+        let mut fb = wall;
+        fb.flood_fill((11, 5), BLUE);
+        assert_eq!(count(&fb, BLUE), 96);
+        assert_eq!(count(&fb, RED), 12);
+
+        // filling the wall only changes the wall
+        let mut fb = wall;
+        fb.flood_fill((3, 11), WHITE);
+        assert_eq!(count(&fb, WHITE), 12);
+        assert_eq!(count(&fb, RED), 0);
+    }
+
+    #[test]
+    fn flood_fill_does_not_cross_diagonals() {
+        let mut fb = Framebuffer::new();
+        fb.draw_line((0, 3), (3, 0), RED);
+        fb.flood_fill((0, 0), BLUE);
+        assert_eq!(count(&fb, BLUE), 6);
+        assert!(fb
+            .pixels()
+            .all(|((x, y), color)| (color == BLUE) == (x + y < 3)));
+    }
+
+    #[test]
+    fn flood_fill_outside_display_or_same_color() {
+        let mut wall = Framebuffer::new();
+        wall.draw_line((3, 0), (3, 11), RED);
+
+        let mut fb = wall;
+        fb.flood_fill((-1, 0), BLUE);
+        fb.flood_fill((0, 12), BLUE);
+        fb.flood_fill((12, 5), BLUE);
+        fb.flood_fill((3, 3), RED);
+        assert_eq!(fb, wall);
+    }
+}
@@ -0,0 +1,274 @@
+//! Background display refresh from a timer interrupt
+//!
+//! Normally the application must call [`ArcadeCoder::scan`] in a tight loop, so any slow work causes the display to
+//! flicker. [`Refresh`] instead owns the driver and advances it a step at a time with [`ArcadeCoder::scan_tick`] from
+//! a timer interrupt, publishing debounced [`ButtonEvent`]s to a queue that the main loop can read. Each tick returns
+//! without waiting for the display, and the next one is due after [`Refresh::next_tick_delay`].
+//!
+//! All shared state is protected by a [`critical_section::Mutex`], so it is safe to use from both the interrupt and the
+//! main loop. The driver is taken out of the lock while it is ticked, so the lock is only held to hand over frames and
+//! button events. On the ESP32, [`Esp32Refresh`](crate::Esp32Refresh) also takes care of setting up the timer.
+//!
+//! Alternatively, [`Refresh::run`] refreshes the display in a loop so it can be run on a core of its own. On the
+//! ESP32, [`Refresh::start_app_core`] does this on the otherwise idle APP (second) core, which is also safe as the
+//! critical section is shared between cores.
+//!
+//! This requires the `refresh` feature.
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use heapless::Deque;
+
+use crate::config::ArcadeCoderConfig;
+use crate::double_buffer::DoubleBuffer;
+use crate::framebuffer::Framebuffer;
+use crate::spi::DisplaySpi;
+use crate::{ArcadeCoder, ButtonEvent, Error};
+
+/// State shared between the main loop and an interrupt.
+pub(crate) type Shared<T> = Mutex<RefCell<T>>;
+
+/// Time to wait between ticks when no driver is installed, in microseconds.
+const IDLE_TICK_DELAY: u32 = ArcadeCoderConfig::arcade_coder().channel_on_time();
+
+/// Shared driver state for refreshing the display from an interrupt.
+///
+/// `N` is the number of button events that can be queued before new events are dropped.
+pub struct Refresh<SPI, O, I, D, const N: usize> {
+    slot: Shared<Slot<ArcadeCoder<SPI, O, I, D>>>,
+    frame: Shared<Option<Framebuffer>>,
+    events: Shared<Deque<ButtonEvent, N>>,
+}
+
+/// The installed driver, which is taken out while it is being ticked.
+struct Slot<T> {
+    driver: Option<T>,
+    ticking: bool,
+    next_tick_delay: u32,
+}
+
+impl<SPI, O, I, D, const N: usize> Default for Refresh<SPI, O, I, D, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<SPI, O, I, D, const N: usize> Refresh<SPI, O, I, D, N> {
+    /// Create a new refresh engine with no driver. This can be used to initialise a `static`.
+    pub const fn new() -> Self {
+        Self {
+            slot: Mutex::new(RefCell::new(Slot {
+                driver: None,
+                ticking: false,
+                next_tick_delay: IDLE_TICK_DELAY,
+            })),
+            frame: Mutex::new(RefCell::new(None)),
+            events: Mutex::new(RefCell::new(Deque::new())),
+        }
+    }
+
+    /// Take the driver back from the refresh engine, stopping the refresh.
+    pub fn uninstall(&self) -> Option<ArcadeCoder<SPI, O, I, D>> {
+        self.with_slot(|slot| {
+            slot.next_tick_delay = IDLE_TICK_DELAY;
+            slot.driver.take()
+        })
+    }
+
+    /// Run a function with access to the driver, for example to change settings.
+    ///
+    /// The refresh is paused while the function runs, so keep it short. Returns `None` if no driver is installed.
+    ///
+    /// If a tick is in progress on another core, this waits for it to finish. It must not be called from an
+    /// interrupt that can interrupt a tick on the same core, as the tick could never finish.
+    pub fn with<R>(&self, f: impl FnOnce(&mut ArcadeCoder<SPI, O, I, D>) -> R) -> Option<R> {
+        self.with_slot(|slot| slot.driver.as_mut().map(f))
+    }
+
+    /// Run a function with the driver slot once no tick is in progress.
+    fn with_slot<R>(&self, f: impl FnOnce(&mut Slot<ArcadeCoder<SPI, O, I, D>>) -> R) -> R {
+        let mut f = Some(f);
+        loop {
+            let result = critical_section::with(|cs| {
+                let mut slot = self.slot.borrow_ref_mut(cs);
+                if slot.ticking {
+                    return None;
+                }
+                f.take().map(|f| f(&mut slot))
+            });
+
+            if let Some(result) = result {
+                return result;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Run a function with access to the display's framebuffers.
+    ///
+    /// The refresh is paused while the function runs, so prefer drawing to a separate [`Framebuffer`] and passing it
+    /// to [`present`](Self::present).
+    pub fn with_framebuffer<R>(
+        &self,
+        f: impl FnOnce(&mut DoubleBuffer<Framebuffer>) -> R,
+    ) -> Option<R> {
+        self.with(|ac| f(&mut ac.framebuffer))
+    }
+
+    /// Show a frame from the start of the next display refresh.
+    ///
+    /// The frame is handed over without waiting for a tick to finish, so this can be called at any time.
+    pub fn present(&self, frame: &Framebuffer) {
+        critical_section::with(|cs| {
+            self.frame.borrow_ref_mut(cs).replace(*frame);
+        });
+    }
+
+    /// Take the oldest button event from the queue.
+    pub fn pop_event(&self) -> Option<ButtonEvent> {
+        critical_section::with(|cs| self.events.borrow_ref_mut(cs).pop_front())
+    }
+
+    /// Get the time to wait before the next [`tick`](Self::tick), in microseconds.
+    pub fn next_tick_delay(&self) -> u32 {
+        critical_section::with(|cs| self.slot.borrow_ref(cs).next_tick_delay)
+    }
+}
+
+impl<SPI, O, I, D, const N: usize> Refresh<SPI, O, I, D, N>
+where
+    SPI: DisplaySpi,
+    O: OutputPin,
+    I: InputPin,
+    D: DelayNs,
+{
+    /// Give the driver to the refresh engine. Ticks before this is called do nothing.
+    pub fn install(&self, ac: ArcadeCoder<SPI, O, I, D>) {
+        self.with_slot(|slot| {
+            slot.next_tick_delay = ac.next_tick_delay();
+            slot.driver.replace(ac);
+        });
+    }
+
+    /// Advance the display by one step. This should be called from a timer interrupt, with the next call due after
+    /// [`next_tick_delay`](Self::next_tick_delay) microseconds.
+    ///
+    /// Once a full frame has been scanned, debounced button events are added to the queue.
+    ///
+    /// Errors are ignored and the channel is retried on the next tick. Use [`try_tick`](Self::try_tick) to handle
+    /// them.
+    pub fn tick(&self) {
+        let _ = self.try_tick();
+    }
+
+    /// Advance the display by one step, returning an error if the SPI bus or a pin fails.
+    pub fn try_tick(&self) -> Result<(), Error> {
+        // take the driver and any new frame, so the lock is not held while the display is driven
+        let taken = critical_section::with(|cs| {
+            let mut slot = self.slot.borrow_ref_mut(cs);
+            let ac = slot.driver.take()?;
+            slot.ticking = true;
+            Some((ac, self.frame.borrow_ref_mut(cs).take()))
+        });
+        let Some((mut ac, frame)) = taken else {
+            return Ok(());
+        };
+
+        if let Some(frame) = frame {
+            *ac.framebuffer.back_mut() = frame;
+            ac.framebuffer.present();
+        }
+
+        let mut events = Deque::<ButtonEvent, N>::new();
+        let result = ac.try_scan_tick().map(|frame_complete| {
+            if frame_complete {
+                ac.handle_input_events(|e| {
+                    // drop events if the queue is full
+                    let _ = events.push_back(e);
+                });
+            }
+        });
+
+        // hand back the driver and publish the events
+        critical_section::with(|cs| {
+            let mut queue = self.events.borrow_ref_mut(cs);
+            while let Some(e) = events.pop_front() {
+                let _ = queue.push_back(e);
+            }
+
+            let mut slot = self.slot.borrow_ref_mut(cs);
+            slot.next_tick_delay = ac.next_tick_delay();
+            slot.driver = Some(ac);
+            slot.ticking = false;
+        });
+
+        result
+    }
+
+    /// Refresh the display forever, waiting for [`next_tick_delay`](Self::next_tick_delay) between each
+    /// [`tick`](Self::tick).
+    ///
+    /// The lock is not held while waiting, so this is intended to be run on a core dedicated to the display.
+    pub fn run(&self, mut delay: impl DelayNs) -> ! {
+        loop {
+            self.tick();
+            delay.delay_us(self.next_tick_delay());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::tests::{red_pixel_row, Expected};
+    use crate::driver::InputScanMode;
+    use crate::framebuffer::{CHANNELS, ROW_BYTES};
+    use crate::RED;
+
+    #[test]
+    fn tick_shows_presented_frames_and_queues_events() {
+        // button (0, 0) is pressed, which is the first test on channel 0
+        let mut expected = Expected::new();
+        for _ in 0..2 {
+            expected.show_channel(0, red_pixel_row(3, 4));
+            expected.scan_channel(0, Some(0));
+            for channel in 1..CHANNELS {
+                expected.show_channel(channel, [0xff; ROW_BYTES]);
+                expected.scan_channel(channel, None);
+            }
+        }
+        expected.show_channel(0, red_pixel_row(3, 4));
+
+        let (mut ac, mocks) = expected.build();
+        ac.input_scan_mode = InputScanMode::PerChannel;
+        ac.apply_config(&ArcadeCoderConfig::arcade_coder().with_debounce_reads(1))
+            .unwrap();
+
+        let refresh: Refresh<_, _, _, _, 4> = Refresh::new();
+        refresh.install(ac);
+
+        let mut frame = Framebuffer::new();
+        frame.set_pixel((0, 0), RED);
+        refresh.present(&frame);
+
+        // events are published once a frame has been scanned, and the press is read the same way for two frames
+        for _ in 0..2 * CHANNELS {
+            refresh.try_tick().unwrap();
+            assert_eq!(refresh.next_tick_delay(), 1388);
+            assert_eq!(refresh.pop_event(), None);
+        }
+        refresh.try_tick().unwrap();
+        assert_eq!(refresh.pop_event(), Some(ButtonEvent::Pressed(0, 0)));
+        assert_eq!(refresh.pop_event(), None);
+
+        // the driver is handed back after each tick
+        assert_eq!(refresh.with(|ac| ac.brightness), Some(u8::MAX));
+        assert!(refresh.uninstall().is_some());
+        refresh.tick();
+
+        mocks.done();
+    }
+}
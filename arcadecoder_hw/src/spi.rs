@@ -0,0 +1,35 @@
+//! SPI bus used to shift data out to the display
+//!
+//! The driver only ever writes to the SPI bus, and it can do other work (like waiting for the display or inputs) while
+//! a write is in progress. [`DisplaySpi`] lets a write be queued and waited on later.
+//!
+//! Any [`embedded_hal::spi::SpiBus`] can be used directly. On the ESP32, enabling the `dma` feature provides
+//! [`Esp32DmaSpi`](crate::Esp32DmaSpi) which ships the data using DMA without involving the CPU.
+
+use embedded_hal::spi::SpiBus;
+
+/// An SPI bus that can queue writes to the display.
+pub trait DisplaySpi {
+    /// Error type returned by the bus.
+    type Error: embedded_hal::spi::Error;
+
+    /// Start writing data to the bus. This may return before the write has completed.
+    ///
+    /// If a previous write is still in progress, this waits for it to complete first.
+    fn start_write(&mut self, words: &[u8]) -> Result<(), Self::Error>;
+
+    /// Wait for the last write to complete.
+    fn wait(&mut self) -> Result<(), Self::Error>;
+}
+
+impl<T: SpiBus> DisplaySpi for T {
+    type Error = T::Error;
+
+    fn start_write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.write(words)
+    }
+
+    fn wait(&mut self) -> Result<(), Self::Error> {
+        self.flush()
+    }
+}
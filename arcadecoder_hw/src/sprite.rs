@@ -0,0 +1,396 @@
+//! Small images for icons and animated characters
+//!
+//! A [`Sprite`] stores 3 bits per pixel (red, green then blue) packed in rows from the top-left, with an optional mask
+//! of 1 bit per pixel where a `1` is drawn and a `0` is transparent. A [`SpriteSheet`] holds several frames of the same
+//! size one after another, each starting on a byte boundary.
+//!
+//! Sprites are drawn with [`Framebuffer::draw_sprite`] at signed positions, so they can be partly off any edge of the
+//! display.
+//!
+//! ## Example
+//! ```
+//! use arcadecoder_hw::{framebuffer::Framebuffer, sprite::Sprite, BLUE, GREEN, RED};
+//!
+//! // red, green on the first row, then blue and a transparent pixel
+//! const ICON: Sprite = Sprite::new(2, 2, &[0b100_010_00, 0b1_111_0000]).with_mask(&[0b1110_0000]);
+//!
+//! let mut fb = Framebuffer::new();
+//! fb.draw_sprite(&ICON.with_flip_horizontal(true), (-1, 0));
+//!
+//! // only the right-hand column is on the display, and it was the left-hand column before flipping
+//! assert_eq!(fb.get_pixel((0, 0)), Some(RED));
+//! assert_eq!(fb.get_pixel((0, 1)), Some(BLUE));
+//! ```
+
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Point, Rect};
+use crate::Color;
+
+/// Number of bits used for each pixel's color.
+const COLOR_BITS: usize = 3;
+
+/// A packed 3-bit image with optional transparency.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sprite<'a> {
+    width: u8,
+    height: u8,
+    pixels: &'a [u8],
+    mask: Option<&'a [u8]>,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+}
+
+/// Frames of the same size for a [`Sprite`] animation, stored one after another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpriteSheet<'a> {
+    width: u8,
+    height: u8,
+    frames: usize,
+    pixels: &'a [u8],
+    mask: Option<&'a [u8]>,
+}
+
+impl<'a> Sprite<'a> {
+    /// Create a sprite with no transparent pixels.
+    pub const fn new(width: u8, height: u8, pixels: &'a [u8]) -> Self {
+        Self {
+            width,
+            height,
+            pixels,
+            mask: None,
+            flip_horizontal: false,
+            flip_vertical: false,
+        }
+    }
+
+    /// Set the mask for which pixels are drawn.
+    pub const fn with_mask(mut self, mask: &'a [u8]) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Set whether the sprite is flipped left to right when it is drawn.
+    pub const fn with_flip_horizontal(mut self, flip: bool) -> Self {
+        self.flip_horizontal = flip;
+        self
+    }
+
+    /// Set whether the sprite is flipped top to bottom when it is drawn.
+    pub const fn with_flip_vertical(mut self, flip: bool) -> Self {
+        self.flip_vertical = flip;
+        self
+    }
+
+    /// Width of the sprite in pixels.
+    pub const fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    /// Height of the sprite in pixels.
+    pub const fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    /// Get the color of a pixel after flipping, or `None` if it is transparent or outside the sprite.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+
+        let x = if self.flip_horizontal {
+            self.width() - 1 - x
+        } else {
+            x
+        };
+        let y = if self.flip_vertical {
+            self.height() - 1 - y
+        } else {
+            y
+        };
+        let index = y * self.width() + x;
+
+        if let Some(mask) = self.mask {
+            if !bit(mask, index) {
+                return None;
+            }
+        }
+
+        let start = index * COLOR_BITS;
+        Some(Color::from_rgb(
+            bit(self.pixels, start),
+            bit(self.pixels, start + 1),
+            bit(self.pixels, start + 2),
+        ))
+    }
+}
+
+impl<'a> SpriteSheet<'a> {
+    /// Create a sprite sheet of `frames` frames, with no transparent pixels.
+    pub const fn new(width: u8, height: u8, frames: usize, pixels: &'a [u8]) -> Self {
+        Self {
+            width,
+            height,
+            frames,
+            pixels,
+            mask: None,
+        }
+    }
+
+    /// Set the masks for which pixels are drawn, with the mask for each frame starting on a byte boundary.
+    pub const fn with_mask(mut self, mask: &'a [u8]) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Number of frames in the sheet.
+    pub const fn len(&self) -> usize {
+        self.frames
+    }
+
+    /// Whether the sheet has no frames.
+    pub const fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Get a frame, or `None` if the index is past the last frame.
+    ///
+    /// ## Example
+    /// ```
+    /// use arcadecoder_hw::sprite::SpriteSheet;
+    ///
+    /// // two 1x1 frames, red then green
+    /// const BLINK: SpriteSheet = SpriteSheet::new(1, 1, 2, &[0b100_00000, 0b010_00000]);
+    ///
+    /// let tick = 3;
+    /// let frame = BLINK.frame(tick % BLINK.len()).unwrap();
+    /// assert_eq!(frame.pixel(0, 0), Some(arcadecoder_hw::GREEN));
+    /// ```
+    pub fn frame(&self, index: usize) -> Option<Sprite<'a>> {
+        if index >= self.frames {
+            return None;
+        }
+
+        let pixels = usize::from(self.width) * usize::from(self.height);
+        let color_bytes = (pixels * COLOR_BITS).div_ceil(8);
+        let mask_bytes = pixels.div_ceil(8);
+
+        let sprite = Sprite::new(
+            self.width,
+            self.height,
+            self.pixels.get(index.checked_mul(color_bytes)?..)?,
+        );
+        Some(match self.mask {
+            Some(mask) => sprite.with_mask(mask.get(index.checked_mul(mask_bytes)?..)?),
+            None => sprite,
+        })
+    }
+}
+
+impl Framebuffer {
+    /// Draw a sprite with its top-left corner at a position, skipping transparent pixels and anything off the display.
+    pub fn draw_sprite(&mut self, sprite: &Sprite, pos: impl Into<Point>) {
+        let pos = pos.into();
+
+        // only visit the part of the sprite that is on the display
+        let area = Rect::new(pos.x, pos.y, sprite.width as u32, sprite.height as u32);
+        let Some(visible) = area.intersection(&Rect::DISPLAY) else {
+            return;
+        };
+
+        for y in visible.y..visible.y + visible.height as i32 {
+            for x in visible.x..visible.x + visible.width as i32 {
+                let (sx, sy) = (x.abs_diff(pos.x) as usize, y.abs_diff(pos.y) as usize);
+                if let Some(color) = sprite.pixel(sx, sy) {
+                    self.set_pixel((x, y), color);
+                }
+            }
+        }
+    }
+}
+
+/// Whether a bit is set, counting from the most significant bit of the first byte. Bits past the end are unset.
+fn bit(data: &[u8], index: usize) -> bool {
+    data.get(index / 8)
+        .is_some_and(|byte| byte & (0x80 >> (index % 8)) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, WHITE, YELLOW};
+
+    /// Red, green and blue on the first row, then yellow, cyan and a transparent (white) pixel.
+    ///
+    /// The color bits are 100 010 001, 110 011 111.
+    const SPRITE: Sprite = Sprite::new(3, 2, &[0x88, 0xe7, 0xc0]).with_mask(&[0xf8]);
+
+    /// Two 2x1 frames: red and green (100 010), then transparent (blue) and white (001 111).
+    const SHEET: SpriteSheet = SpriteSheet::new(2, 1, 2, &[0x88, 0x3c]).with_mask(&[0xc0, 0x40]);
+
+    fn rows(sprite: &Sprite) -> [[Option<Color>; 3]; 2] {
+        core::array::from_fn(|y| core::array::from_fn(|x| sprite.pixel(x, y)))
+    }
+
+    /// Draw a sprite, returning the pixels in the top-left 3x2 corner of the display and how many pixels were drawn.
+    fn draw(sprite: &Sprite, pos: (i32, i32)) -> ([[Color; 3]; 2], usize) {
+        let mut fb = Framebuffer::new();
+        fb.draw_sprite(sprite, pos);
+        let corner =
+            core::array::from_fn(|y| core::array::from_fn(|x| fb.get_pixel((x, y)).unwrap()));
+        (
+            corner,
+            fb.pixels().filter(|(_, color)| *color != BLACK).count(),
+        )
+    }
+
+    #[test]
+    fn pixels() {
+        assert_eq!(
+            rows(&SPRITE),
+            [
+                [Some(RED), Some(GREEN), Some(BLUE)],
+                [Some(YELLOW), Some(CYAN), None]
+            ]
+        );
+        assert_eq!(SPRITE.pixel(3, 0), None);
+        assert_eq!(SPRITE.pixel(0, 2), None);
+
+        // without the mask every pixel is drawn
+        let opaque = Sprite::new(3, 2, SPRITE.pixels);
+        assert_eq!(opaque.pixel(2, 1), Some(WHITE));
+    }
+
+    #[test]
+    fn flipped_pixels() {
+        assert_eq!(
+            rows(&SPRITE.with_flip_horizontal(true)),
+            [
+                [Some(BLUE), Some(GREEN), Some(RED)],
+                [None, Some(CYAN), Some(YELLOW)]
+            ]
+        );
+        assert_eq!(
+            rows(&SPRITE.with_flip_vertical(true)),
+            [
+                [Some(YELLOW), Some(CYAN), None],
+                [Some(RED), Some(GREEN), Some(BLUE)]
+            ]
+        );
+        assert_eq!(
+            rows(&SPRITE.with_flip_horizontal(true).with_flip_vertical(true)),
+            [
+                [None, Some(CYAN), Some(YELLOW)],
+                [Some(BLUE), Some(GREEN), Some(RED)]
+            ]
+        );
+        assert_eq!(SPRITE.with_flip_horizontal(true).pixel(3, 0), None);
+    }
+
+    #[test]
+    fn draw_on_display() {
+        assert_eq!(
+            draw(&SPRITE, (0, 0)),
+            ([[RED, GREEN, BLUE], [YELLOW, CYAN, BLACK]], 5)
+        );
+
+        // transparent pixels keep what was there
+        let mut fb = Framebuffer::new();
+        fb.fill(MAGENTA);
+        fb.draw_sprite(&SPRITE, (9, 10));
+        assert_eq!(fb.get_pixel((9, 10)), Some(RED));
+        assert_eq!(fb.get_pixel((11, 11)), Some(MAGENTA));
+    }
+
+    #[test]
+    fn draw_at_negative_offsets() {
+        assert_eq!(
+            draw(&SPRITE, (-1, -1)),
+            ([[CYAN, BLACK, BLACK], [BLACK; 3]], 1)
+        );
+        assert_eq!(
+            draw(&SPRITE, (-2, 0)),
+            ([[BLUE, BLACK, BLACK], [BLACK; 3]], 1)
+        );
+        assert_eq!(
+            draw(&SPRITE, (0, -1)),
+            ([[YELLOW, CYAN, BLACK], [BLACK; 3]], 2)
+        );
+    }
+
+    #[test]
+    fn draw_flipped_at_negative_offsets() {
+        assert_eq!(
+            draw(&SPRITE.with_flip_horizontal(true), (-1, 0)),
+            ([[GREEN, RED, BLACK], [CYAN, YELLOW, BLACK]], 4)
+        );
+        assert_eq!(
+            draw(&SPRITE.with_flip_vertical(true), (0, -1)),
+            ([[RED, GREEN, BLUE], [BLACK; 3]], 3)
+        );
+        assert_eq!(
+            draw(
+                &SPRITE.with_flip_horizontal(true).with_flip_vertical(true),
+                (-2, -1)
+            ),
+            ([[RED, BLACK, BLACK], [BLACK; 3]], 1)
+        );
+    }
+
+    #[test]
+    fn draw_off_display() {
+        for pos in [
+            (-3, 0),
+            (0, -2),
+            (12, 0),
+            (0, 12),
+            (i32::MIN, i32::MIN),
+            (i32::MAX, i32::MAX),
+        ] {
+            assert_eq!(draw(&SPRITE, pos).1, 0, "{pos:?}");
+        }
+
+        // only the top-left pixel is on the display
+        let mut fb = Framebuffer::new();
+        fb.draw_sprite(&SPRITE, (11, 11));
+        assert_eq!(fb.get_pixel((11, 11)), Some(RED));
+        assert_eq!(fb.pixels().filter(|(_, color)| *color != BLACK).count(), 1);
+    }
+
+    #[test]
+    fn sheet_frames() {
+        assert_eq!(SHEET.len(), 2);
+        assert!(!SHEET.is_empty());
+
+        let first = SHEET.frame(0).unwrap();
+        assert_eq!(
+            (first.pixel(0, 0), first.pixel(1, 0)),
+            (Some(RED), Some(GREEN))
+        );
+        let second = SHEET.frame(1).unwrap();
+        assert_eq!(
+            (second.pixel(0, 0), second.pixel(1, 0)),
+            (None, Some(WHITE))
+        );
+    }
+
+    #[test]
+    fn sheet_frames_out_of_range() {
+        assert_eq!(SHEET.frame(2), None);
+        assert_eq!(SHEET.frame(usize::MAX), None);
+
+        let empty = SpriteSheet::new(2, 1, 0, &[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.frame(0), None);
+
+        // more frames than there is data for, where bits past the end of the data are unset
+        let short = SpriteSheet::new(2, 1, 4, SHEET.pixels).with_mask(&[0xff]);
+        assert_eq!(short.frame(0).unwrap().pixel(0, 0), Some(RED));
+        assert_eq!(short.frame(1).unwrap().pixel(0, 0), None);
+        assert_eq!(short.frame(2), None);
+        assert_eq!(short.frame(3), None);
+        assert_eq!(
+            SpriteSheet::new(4, 4, usize::MAX, &[]).frame(usize::MAX - 1),
+            None
+        );
+    }
+}
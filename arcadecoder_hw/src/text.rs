@@ -0,0 +1,235 @@
+//! Laying out text and numbers
+//!
+//! [`Framebuffer::draw_text`](crate::framebuffer::Framebuffer::draw_text) draws a string relative to an anchor point,
+//! with the [`Alignment`] deciding whether the text starts, is centred on, or ends at the anchor:
+//!
+//! ```
+//! use arcadecoder_hw::{font::FONT_3X5, framebuffer::Framebuffer, text::Alignment, WHITE};
+//!
+//! let mut fb = Framebuffer::new();
+//! // "HI" is 7 pixels wide, so it ends in the right-most column
+//! fb.draw_text("HI", &FONT_3X5, (11, 0), Alignment::Right, WHITE);
+//! assert_eq!(FONT_3X5.measure_text("HI"), 7);
+//! ```
+//!
+//! Anything outside the display, or outside the area given to
+//! [`draw_text_in`](crate::framebuffer::Framebuffer::draw_text_in), is clipped. Extra space between characters can be
+//! added with [`Font::with_letter_spacing`](crate::font::Font::with_letter_spacing).
+
+use crate::geometry::Rect;
+
+/// Horizontal alignment of text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Alignment {
+    /// The text starts at the anchor.
+    #[default]
+    Left,
+
+    /// The text is centred on the anchor.
+    Center,
+
+    /// The text ends at the anchor.
+    Right,
+}
+
+impl Alignment {
+    /// Get the left edge of text `width` pixels wide, aligned to an anchor.
+    pub(crate) fn start(self, anchor: i32, width: usize) -> i32 {
+        let width = i32::try_from(width).unwrap_or(i32::MAX);
+        match self {
+            Self::Left => anchor,
+            Self::Center => anchor.saturating_sub(width / 2),
+            Self::Right => anchor.saturating_sub(width).saturating_add(1),
+        }
+    }
+
+    /// Get the left edge of text `width` pixels wide, aligned within an area.
+    pub(crate) fn start_in(self, area: Rect, width: usize) -> i32 {
+        let space = i64::from(area.width) - i64::try_from(width).unwrap_or(i64::MAX);
+        let offset = match self {
+            Self::Left => 0,
+            Self::Center => space.div_euclid(2),
+            Self::Right => space,
+        };
+        i32::try_from(i64::from(area.x) + offset).unwrap_or(i32::MIN)
+    }
+}
+
+/// How many digits [`Framebuffer::draw_number`](crate::framebuffer::Framebuffer::draw_number) draws.
+///
+/// Numbers with more digits than this are drawn in full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Digits {
+    /// Pad the number to this many digits with leading zeros, like `007`.
+    Zeros(u8),
+
+    /// Pad the number to this many digits with blank space, like `  7`. The number is aligned as if every digit was
+    /// drawn, so it stays in the same place as it changes.
+    Blank(u8),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::{FONT_3X5, FONT_5X5};
+    use crate::framebuffer::Framebuffer;
+    use crate::geometry::Point;
+    use crate::{BLACK, WHITE};
+
+    /// Draw characters one at a time from a position, as the text functions should.
+    fn draw_chars(text: &str, start_pos: (i32, i32)) -> Framebuffer {
+        let mut fb = Framebuffer::new();
+        let mut x = start_pos.0;
+        for c in text.chars() {
+            x += fb.draw_char(c, &FONT_3X5, (x, start_pos.1), WHITE) as i32;
+        }
+        fb
+    }
+
+    /// Draw a number in white.
+    fn number(n: u32, digits: Digits, anchor: (i32, i32), alignment: Alignment) -> Framebuffer {
+        let mut fb = Framebuffer::new();
+        fb.draw_number(n, digits, &FONT_3X5, anchor, alignment, WHITE);
+        fb
+    }
+
+    #[test]
+    fn measure_text() {
+        assert_eq!(FONT_3X5.measure_text(""), 0);
+        assert_eq!(FONT_3X5.measure_text("1"), 3);
+        // the gap after the last character is not counted
+        assert_eq!(FONT_3X5.measure_text("12"), 7);
+        assert_eq!(FONT_3X5.measure_text("1 2"), 9);
+        assert_eq!(FONT_5X5.measure_text("12"), 11);
+
+        // characters that are not in the font are skipped, and lowercase falls back to uppercase
+        assert_eq!(FONT_3X5.measure_text("1\u{e9}2"), 7);
+        assert_eq!(FONT_3X5.measure_text("hi"), FONT_3X5.measure_text("HI"));
+
+        assert_eq!(FONT_3X5.with_letter_spacing(1).measure_text("12"), 8);
+        assert_eq!(FONT_3X5.with_letter_spacing(-1).measure_text("12"), 6);
+    }
+
+    #[test]
+    fn align_to_anchor() {
+        assert_eq!(Alignment::Left.start(5, 7), 5);
+        assert_eq!(Alignment::Center.start(6, 7), 3);
+        assert_eq!(Alignment::Right.start(11, 7), 5);
+        assert_eq!(Alignment::Right.start(0, 0), 1);
+        assert_eq!(Alignment::Center.start(i32::MIN, usize::MAX), i32::MIN);
+    }
+
+    #[test]
+    fn align_in_area() {
+        let area = Rect::new(2, 0, 8, 5);
+        assert_eq!(Alignment::Left.start_in(area, 3), 2);
+        assert_eq!(Alignment::Center.start_in(area, 3), 4);
+        assert_eq!(Alignment::Right.start_in(area, 3), 7);
+
+        // text wider than the area overflows on both sides when centred
+        assert_eq!(Alignment::Left.start_in(area, 10), 2);
+        assert_eq!(Alignment::Center.start_in(area, 10), 1);
+        assert_eq!(Alignment::Right.start_in(area, 10), 0);
+    }
+
+    #[test]
+    fn draw_aligned_text() {
+        let mut fb = Framebuffer::new();
+        fb.draw_text("12", &FONT_3X5, (0, 0), Alignment::Left, WHITE);
+        assert_eq!(fb, draw_chars("12", (0, 0)));
+
+        let mut fb = Framebuffer::new();
+        fb.draw_text("12", &FONT_3X5, (6, 3), Alignment::Center, WHITE);
+        assert_eq!(fb, draw_chars("12", (3, 3)));
+
+        // the last column of the text is on the anchor
+        let mut fb = Framebuffer::new();
+        fb.draw_text("12", &FONT_3X5, (11, 7), Alignment::Right, WHITE);
+        assert_eq!(fb, draw_chars("12", (5, 7)));
+        assert!((0..12).any(|y| fb.get_pixel((11, y)) == Some(WHITE)));
+    }
+
+    #[test]
+    fn clip_text_to_display() {
+        // the start of the text is off the left of the display
+        let mut fb = Framebuffer::new();
+        fb.draw_text("12", &FONT_3X5, (2, 0), Alignment::Right, WHITE);
+        assert_eq!(fb, draw_chars("12", (-4, 0)));
+        assert_ne!(fb, Framebuffer::new());
+
+        // the second character is entirely off the right of the display
+        let mut fb = Framebuffer::new();
+        fb.draw_text("12", &FONT_3X5, (9, 9), Alignment::Left, WHITE);
+        assert_eq!(fb, draw_chars("1", (9, 9)));
+
+        let mut fb = Framebuffer::new();
+        fb.draw_text("12", &FONT_3X5, (20, 0), Alignment::Left, WHITE);
+        fb.draw_text("12", &FONT_3X5, (0, -5), Alignment::Left, WHITE);
+        assert_eq!(fb, Framebuffer::new());
+    }
+
+    #[test]
+    fn clip_text_to_area() {
+        let area = Rect::new(2, 2, 5, 5);
+
+        // "12" is 7 pixels wide, so it overflows the area by a pixel on each side
+        let mut fb = Framebuffer::new();
+        fb.draw_text_in("12", &FONT_3X5, area, Alignment::Center, WHITE);
+
+        let mut expected = draw_chars("12", (1, 2));
+        for ((x, y), _) in Framebuffer::new().pixels() {
+            let pos = Point::new(x as i32, y as i32);
+            if !area.contains(pos) {
+                expected.set_pixel(pos, BLACK);
+            }
+        }
+        assert_eq!(fb, expected);
+        assert!(fb.pixels().all(|((x, y), color)| {
+            area.contains(Point::new(x as i32, y as i32)) || color == BLACK
+        }));
+    }
+
+    #[test]
+    fn draw_numbers_with_zeros() {
+        assert_eq!(
+            number(7, Digits::Zeros(3), (0, 0), Alignment::Left),
+            draw_chars("007", (0, 0))
+        );
+
+        assert_eq!(
+            number(0, Digits::Zeros(0), (0, 0), Alignment::Left),
+            draw_chars("0", (0, 0))
+        );
+
+        // numbers with more digits are drawn in full
+        assert_eq!(
+            number(1234, Digits::Zeros(2), (11, 0), Alignment::Right),
+            draw_chars("1234", (-3, 0))
+        );
+    }
+
+    #[test]
+    fn draw_numbers_with_blanks() {
+        // leading zeros are left out, but the number is placed as if they were drawn
+        assert_eq!(
+            number(7, Digits::Blank(3), (0, 0), Alignment::Left),
+            draw_chars("7", (8, 0))
+        );
+
+        assert_eq!(
+            number(42, Digits::Blank(3), (6, 6), Alignment::Center),
+            draw_chars("42", (5, 6))
+        );
+
+        // zero still has a digit
+        assert_eq!(
+            number(0, Digits::Blank(2), (11, 0), Alignment::Right),
+            draw_chars("0", (9, 0))
+        );
+
+        assert_eq!(
+            number(305, Digits::Blank(3), (0, 0), Alignment::Left),
+            draw_chars("305", (0, 0))
+        );
+    }
+}
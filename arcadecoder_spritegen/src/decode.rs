@@ -0,0 +1,408 @@
+//! Decoding PNG and GIF files to pixels
+
+use core::fmt::Write;
+use std::vec;
+use std::vec::Vec;
+
+use crate::Error;
+#[cfg(feature = "gif")]
+use crate::BYTES_PER_PIXEL;
+
+/// Convert a PNG image to Rust source for a `pub static` sprite called `name`.
+///
+/// Any PNG color type and bit depth can be used, as long as every pixel is a display color or fully transparent.
+#[cfg(feature = "png")]
+pub fn write_png_sprite(out: &mut impl Write, name: &str, png: &[u8]) -> Result<(), Error> {
+    const ERROR: Error = Error::Decode("png");
+
+    let mut decoder = ::png::Decoder::new(png);
+    decoder.set_transformations(::png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|_| ERROR)?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).map_err(|_| ERROR)?;
+    let pixels = &pixels[..info.buffer_size()];
+
+    // palette images are expanded, so there is no indexed color type left
+    let rgba: Vec<u8> = match info.color_type {
+        ::png::ColorType::Rgba => pixels.to_vec(),
+        ::png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xff])
+            .collect(),
+        ::png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        ::png::ColorType::Grayscale => pixels.iter().flat_map(|v| [*v, *v, *v, 0xff]).collect(),
+        ::png::ColorType::Indexed => return Err(ERROR),
+    };
+
+    crate::write_sprite(out, name, info.width as usize, info.height as usize, &rgba)
+}
+
+/// Convert an animated GIF to Rust source for a `pub static` sprite sheet called `name`, with a frame for each frame of
+/// the animation.
+///
+/// Frames are drawn on top of each other as a GIF viewer would, so each frame of the sheet is the whole image. The
+/// time to show each frame for is written as `pub static {name}_DELAYS_MS: [u32; _]`.
+#[cfg(feature = "gif")]
+pub fn write_gif_sheet(out: &mut impl Write, name: &str, gif: &[u8]) -> Result<(), Error> {
+    const ERROR: Error = Error::Decode("gif");
+
+    let mut options = ::gif::DecodeOptions::new();
+    options.set_color_output(::gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(gif).map_err(|_| ERROR)?;
+    let (width, height) = (usize::from(decoder.width()), usize::from(decoder.height()));
+
+    let mut canvas = vec![0; width * height * BYTES_PER_PIXEL];
+    let mut frames = Vec::new();
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().map_err(|_| ERROR)? {
+        let previous = canvas.clone();
+        let (left, top) = (usize::from(frame.left), usize::from(frame.top));
+        let frame_width = usize::from(frame.width);
+
+        // each frame only covers part of the image, and its transparent pixels show the frames before it
+        let mut area = Vec::new();
+        for (i, pixel) in frame.buffer.chunks_exact(BYTES_PER_PIXEL).enumerate() {
+            let (x, y) = (left + i % frame_width, top + i / frame_width);
+            if x < width && y < height {
+                let start = (y * width + x) * BYTES_PER_PIXEL;
+                area.push(start);
+                if pixel[3] != 0 {
+                    canvas[start..start + BYTES_PER_PIXEL].copy_from_slice(pixel);
+                }
+            }
+        }
+
+        frames.push(canvas.clone());
+        // delays are in hundredths of a second
+        delays.push(u32::from(frame.delay) * 10);
+
+        match frame.dispose {
+            ::gif::DisposalMethod::Background => {
+                for start in area {
+                    canvas[start..start + BYTES_PER_PIXEL].fill(0);
+                }
+            }
+            ::gif::DisposalMethod::Previous => canvas = previous,
+            ::gif::DisposalMethod::Any | ::gif::DisposalMethod::Keep => {}
+        }
+    }
+
+    let frames: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+    crate::write_sprite_sheet(out, name, width, height, &frames)?;
+
+    writeln!(
+        out,
+        "/// Time to show each frame of [`{name}`] for, in milliseconds."
+    )?;
+    write!(
+        out,
+        "pub static {name}_DELAYS_MS: [u32; {}] = [",
+        delays.len()
+    )?;
+    for (i, delay) in delays.iter().enumerate() {
+        if i > 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "{delay}")?;
+    }
+    writeln!(out, "];")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::String;
+
+    const RED: [u8; 4] = [0xff, 0, 0, 0xff];
+    #[cfg(feature = "gif")]
+    const GREEN: [u8; 4] = [0, 0xff, 0, 0xff];
+    const BLUE: [u8; 4] = [0, 0, 0xff, 0xff];
+    const CLEAR: [u8; 4] = [0; 4];
+
+    /// The source written for a sprite sheet of 2x1 frames.
+    #[cfg(feature = "gif")]
+    fn sheet(frames: &[[[u8; 4]; 2]]) -> String {
+        let frames: Vec<Vec<u8>> = frames.iter().map(|f| f.as_flattened().to_vec()).collect();
+        let frames: Vec<&[u8]> = frames.iter().map(Vec::as_slice).collect();
+        let mut out = String::new();
+        crate::write_sprite_sheet(&mut out, "ANIM", 2, 1, &frames).unwrap();
+        out
+    }
+
+    #[cfg(feature = "png")]
+    fn png(width: u32, height: u32, color: ::png::ColorType, data: &[u8]) -> Vec<u8> {
+        png_with(width, height, color, data, |_| {})
+    }
+
+    #[cfg(feature = "png")]
+    fn png_with(
+        width: u32,
+        height: u32,
+        color: ::png::ColorType,
+        data: &[u8],
+        setup: impl FnOnce(&mut ::png::Encoder<&mut Vec<u8>>),
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = ::png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(::png::BitDepth::Eight);
+        setup(&mut encoder);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        out
+    }
+
+    #[cfg(feature = "png")]
+    fn convert_png(png: &[u8]) -> Result<String, Error> {
+        let mut out = String::new();
+        write_png_sprite(&mut out, "ICON", png)?;
+        Ok(out)
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_color_types() {
+        let mut expected = String::new();
+        crate::write_sprite(&mut expected, "ICON", 2, 1, [RED, BLUE].as_flattened()).unwrap();
+
+        let rgb = png(2, 1, ::png::ColorType::Rgb, &[0xff, 0, 0, 0, 0, 0xff]);
+        assert_eq!(convert_png(&rgb), Ok(expected.clone()));
+
+        let rgba = png(2, 1, ::png::ColorType::Rgba, [RED, BLUE].as_flattened());
+        assert_eq!(convert_png(&rgba), Ok(expected.clone()));
+
+        let indexed = png_with(2, 1, ::png::ColorType::Indexed, &[1, 0], |encoder| {
+            encoder.set_palette(std::vec![0, 0, 0xff, 0xff, 0, 0]);
+        });
+        assert_eq!(convert_png(&indexed), Ok(expected));
+
+        // black and white, with a transparent pixel
+        let mut expected = String::new();
+        let pixels = [[0xff; 4], CLEAR, [0, 0, 0, 0xff]];
+        crate::write_sprite(&mut expected, "ICON", 3, 1, pixels.as_flattened()).unwrap();
+        let gray = png(
+            3,
+            1,
+            ::png::ColorType::GrayscaleAlpha,
+            &[0xff, 0xff, 0x80, 0, 0, 0xff],
+        );
+        assert_eq!(convert_png(&gray), Ok(expected));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_unsupported_color() {
+        let orange = png(2, 1, ::png::ColorType::Rgb, &[0, 0, 0xff, 0xff, 0x80, 0]);
+        assert_eq!(
+            convert_png(&orange),
+            Err(Error::Color {
+                frame: 0,
+                x: 1,
+                y: 0,
+                rgba: [0xff, 0x80, 0, 0xff]
+            })
+        );
+
+        // partly transparent pixels cannot be drawn
+        let faded = png(
+            1,
+            2,
+            ::png::ColorType::Rgba,
+            &[0, 0, 0, 0, 0xff, 0, 0, 0x80],
+        );
+        assert_eq!(
+            convert_png(&faded),
+            Err(Error::Color {
+                frame: 0,
+                x: 0,
+                y: 1,
+                rgba: [0xff, 0, 0, 0x80]
+            })
+        );
+
+        let gray = png(1, 1, ::png::ColorType::Grayscale, &[0x80]);
+        assert!(matches!(convert_png(&gray), Err(Error::Color { .. })));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_bad_size() {
+        let wide = png(256, 1, ::png::ColorType::Grayscale, &[0; 256]);
+        assert_eq!(
+            convert_png(&wide),
+            Err(Error::Size {
+                width: 256,
+                height: 1
+            })
+        );
+
+        let tall = png(1, 300, ::png::ColorType::Grayscale, &[0; 300]);
+        assert_eq!(
+            convert_png(&tall),
+            Err(Error::Size {
+                width: 1,
+                height: 300
+            })
+        );
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_decode_error() {
+        assert_eq!(convert_png(b"not a png"), Err(Error::Decode("png")));
+
+        let mut truncated = png(2, 1, ::png::ColorType::Rgb, &[0; 6]);
+        truncated.truncate(truncated.len() - 20);
+        assert_eq!(convert_png(&truncated), Err(Error::Decode("png")));
+    }
+
+    /// Palette for GIF fixtures, where index 0 is transparent.
+    #[cfg(feature = "gif")]
+    const PALETTE: [u8; 15] = [0, 0, 0, 0xff, 0, 0, 0, 0xff, 0, 0, 0, 0xff, 0xff, 0x80, 0];
+
+    /// Encode a GIF where each frame is `(left, width, palette indexes, delay, disposal)`.
+    #[cfg(feature = "gif")]
+    fn gif(
+        width: u16,
+        height: u16,
+        frames: &[(u16, u16, &[u8], u16, ::gif::DisposalMethod)],
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+        {
+            let mut encoder = ::gif::Encoder::new(&mut out, width, height, &PALETTE).unwrap();
+            for (left, frame_width, indexes, delay, dispose) in frames {
+                encoder
+                    .write_frame(&::gif::Frame {
+                        left: *left,
+                        width: *frame_width,
+                        height,
+                        buffer: indexes.to_vec().into(),
+                        delay: *delay,
+                        dispose: *dispose,
+                        transparent: Some(0),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+        }
+        out
+    }
+
+    #[cfg(feature = "gif")]
+    fn convert_gif(gif: &[u8]) -> Result<String, Error> {
+        let mut out = String::new();
+        write_gif_sheet(&mut out, "ANIM", gif)?;
+        Ok(out)
+    }
+
+    /// Convert a 2x1 animation of red and green, then blue over the green, then nothing new, disposing of the first
+    /// two frames in different ways.
+    #[cfg(feature = "gif")]
+    fn convert_disposal(
+        first: ::gif::DisposalMethod,
+        second: ::gif::DisposalMethod,
+    ) -> Result<String, Error> {
+        let keep = ::gif::DisposalMethod::Keep;
+        convert_gif(&gif(
+            2,
+            1,
+            &[
+                (0, 2, &[1, 2], 5, first),
+                (1, 1, &[3], 10, second),
+                (1, 1, &[0], 0, keep),
+            ],
+        ))
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_keep() {
+        use ::gif::DisposalMethod::Keep;
+
+        let expected = sheet(&[[RED, GREEN], [RED, BLUE], [RED, BLUE]]);
+        let out = convert_disposal(Keep, Keep).unwrap();
+        assert_eq!(&out[..expected.len()], expected);
+        assert!(out.ends_with("pub static ANIM_DELAYS_MS: [u32; 3] = [50, 100, 0];\n"));
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_dispose_to_background() {
+        use ::gif::DisposalMethod::{Background, Keep};
+
+        // the whole of the first frame is cleared before the second is drawn
+        let expected = sheet(&[[RED, GREEN], [CLEAR, BLUE], [CLEAR, BLUE]]);
+        assert!(convert_disposal(Background, Keep)
+            .unwrap()
+            .starts_with(&expected));
+
+        // only the area of the second frame is cleared
+        let expected = sheet(&[[RED, GREEN], [RED, BLUE], [RED, CLEAR]]);
+        assert!(convert_disposal(Keep, Background)
+            .unwrap()
+            .starts_with(&expected));
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_dispose_to_previous() {
+        use ::gif::DisposalMethod::{Background, Keep, Previous};
+
+        // the blue is drawn over the green, then the green is put back
+        let expected = sheet(&[[RED, GREEN], [RED, BLUE], [RED, GREEN]]);
+        assert!(convert_disposal(Keep, Previous)
+            .unwrap()
+            .starts_with(&expected));
+
+        // going back to the first frame after it was cleared
+        let expected = sheet(&[[RED, GREEN], [CLEAR, BLUE], [CLEAR, CLEAR]]);
+        assert!(convert_disposal(Background, Previous)
+            .unwrap()
+            .starts_with(&expected));
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_unsupported_color() {
+        use ::gif::DisposalMethod::Keep;
+
+        // orange in the second frame
+        let orange = gif(2, 1, &[(0, 2, &[1, 2], 0, Keep), (0, 1, &[4], 0, Keep)]);
+        assert_eq!(
+            convert_gif(&orange),
+            Err(Error::Color {
+                frame: 1,
+                x: 0,
+                y: 0,
+                rgba: [0xff, 0x80, 0, 0xff]
+            })
+        );
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_bad_size() {
+        use ::gif::DisposalMethod::Keep;
+
+        let wide = gif(300, 1, &[(0, 300, &[1; 300], 0, Keep)]);
+        assert_eq!(
+            convert_gif(&wide),
+            Err(Error::Size {
+                width: 300,
+                height: 1
+            })
+        );
+    }
+
+    #[cfg(feature = "gif")]
+    #[test]
+    fn gif_decode_error() {
+        assert_eq!(convert_gif(b"GIF89a"), Err(Error::Decode("gif")));
+        assert_eq!(convert_gif(b"not a gif"), Err(Error::Decode("gif")));
+    }
+}
@@ -0,0 +1,350 @@
+//! Build-time conversion of images to sprites for `arcadecoder_hw`
+//!
+//! Art can be drawn in any image editor instead of being packed into arrays by hand. [`write_sprite`] and
+//! [`write_sprite_sheet`] check the pixels of an image and write Rust source for a `static`
+//! `arcadecoder_hw::sprite::Sprite` or `SpriteSheet`. With the `png` and `gif` features, PNG images and animated GIFs
+//! can be converted directly with [`write_png_sprite`] and [`write_gif_sheet`].
+//!
+//! Every pixel must be one of the 8 display colors (each channel `0x00` or `0xff`) or fully transparent, so the sprite
+//! looks exactly like the image. Anything else is an [`Error`], which fails the build with the position of the pixel.
+//!
+//! This is intended to be used from a build script, with the generated source included in the firmware:
+//!
+//! ```toml
+//! # Cargo.toml
+//! [build-dependencies]
+//! arcadecoder_spritegen = { path = "../arcadecoder_spritegen", features = ["png", "gif"] }
+//! ```
+//!
+//! ```ignore
+//! // build.rs
+//! let mut out = String::new();
+//! for entry in std::fs::read_dir("assets").unwrap() {
+//!     let path = entry.unwrap().path();
+//!     let name = path.file_stem().unwrap().to_str().unwrap().to_uppercase();
+//!     let image = std::fs::read(&path).unwrap();
+//!
+//!     let result = match path.extension().and_then(|ext| ext.to_str()) {
+//!         Some("png") => arcadecoder_spritegen::write_png_sprite(&mut out, &name, &image),
+//!         Some("gif") => arcadecoder_spritegen::write_gif_sheet(&mut out, &name, &image),
+//!         _ => continue,
+//!     };
+//!     if let Err(e) = result {
+//!         panic!("{}: {e}", path.display());
+//!     }
+//! }
+//!
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(format!("{out_dir}/sprites.rs"), out).unwrap();
+//! println!("cargo:rerun-if-changed=assets");
+//! ```
+//!
+//! ```ignore
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/sprites.rs"));
+//!
+//! fb.draw_sprite(&HEART, (4, 4));
+//! fb.draw_sprite(&WALK.frame(tick % WALK.len()).unwrap(), (0, 0));
+//! ```
+//!
+//! Without the `png` and `gif` features the crate does not allocate and has no dependencies, so it builds for any
+//! target.
+
+#![no_std]
+
+#[cfg(any(feature = "png", feature = "gif"))]
+extern crate std;
+
+#[cfg(any(feature = "png", feature = "gif"))]
+mod decode;
+
+use core::fmt::{self, Write};
+
+#[cfg(feature = "gif")]
+pub use decode::write_gif_sheet;
+#[cfg(feature = "png")]
+pub use decode::write_png_sprite;
+
+/// Path to the sprite types in the generated source.
+const SPRITE_PATH: &str = "::arcadecoder_hw::sprite";
+
+/// Number of bytes in each pixel of an image.
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Number of packed bytes written on each line of the generated source.
+const BYTES_PER_LINE: usize = 12;
+
+/// An error converting an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The image file could not be decoded.
+    Decode(&'static str),
+
+    /// The image is empty, or larger than 255 pixels in either direction.
+    Size { width: usize, height: usize },
+
+    /// The pixel buffer is not the right length for the image size.
+    Length { frame: usize },
+
+    /// A pixel is not one of the 8 display colors or fully transparent.
+    Color {
+        frame: usize,
+        x: usize,
+        y: usize,
+        rgba: [u8; 4],
+    },
+
+    /// Writing the output failed.
+    Write,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(format) => write!(f, "could not decode {format} image"),
+            Self::Size { width, height } => write!(
+                f,
+                "image is {width}x{height}, but sprites must be 1 to 255 pixels in each direction"
+            ),
+            Self::Length { frame } => write!(f, "pixels for frame {frame} do not match the image size"),
+            Self::Color {
+                frame,
+                x,
+                y,
+                rgba: [r, g, b, a],
+            } => write!(
+                f,
+                "pixel ({x}, {y}) in frame {frame} is #{r:02x}{g:02x}{b:02x}{a:02x}, which is not a display color \
+                 (each channel 00 or ff) or fully transparent"
+            ),
+            Self::Write => write!(f, "could not write output"),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Self::Write
+    }
+}
+
+/// Convert an image to Rust source for a `pub static` sprite called `name`.
+///
+/// `rgba` has 4 bytes for each pixel (red, green, blue then alpha) in rows from the top-left. The sprite only has a
+/// mask if some pixels are transparent.
+///
+/// ## Example
+/// ```
+/// // a red pixel next to a transparent one
+/// let mut out = String::new();
+/// arcadecoder_spritegen::write_sprite(&mut out, "DOT", 2, 1, &[0xff, 0, 0, 0xff, 0, 0, 0, 0]).unwrap();
+/// assert!(out.contains("Sprite::new("));
+///
+/// // orange is not a display color
+/// let orange = [0xff, 0x80, 0, 0xff];
+/// assert!(arcadecoder_spritegen::write_sprite(&mut out, "ORANGE", 1, 1, &orange).is_err());
+/// ```
+pub fn write_sprite(
+    out: &mut impl Write,
+    name: &str,
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+) -> Result<(), Error> {
+    let (width, height) = check_size(width, height)?;
+    let frame = Frame::new(width, height, rgba, 0)?;
+
+    writeln!(out, "/// Generated by arcadecoder_spritegen.")?;
+    writeln!(
+        out,
+        "pub static {name}: {SPRITE_PATH}::Sprite = {SPRITE_PATH}::Sprite::new("
+    )?;
+    writeln!(out, "    {width},")?;
+    writeln!(out, "    {height},")?;
+    writeln!(out, "    &[")?;
+    write_bits(out, "        ", frame.color_bits())?;
+    writeln!(out, "    ],")?;
+
+    if frame.has_transparency() {
+        writeln!(out, ")")?;
+        writeln!(out, ".with_mask(&[")?;
+        write_bits(out, "    ", frame.mask_bits())?;
+        writeln!(out, "]);")?;
+    } else {
+        writeln!(out, ");")?;
+    }
+    Ok(())
+}
+
+/// Convert frames of the same size to Rust source for a `pub static` sprite sheet called `name`.
+///
+/// Each frame has 4 bytes for each pixel (red, green, blue then alpha) in rows from the top-left. The sheet only has
+/// masks if some pixels are transparent.
+pub fn write_sprite_sheet(
+    out: &mut impl Write,
+    name: &str,
+    width: usize,
+    height: usize,
+    frames: &[&[u8]],
+) -> Result<(), Error> {
+    let (width, height) = check_size(width, height)?;
+
+    // check every frame before writing anything
+    let mut transparent = false;
+    for (i, rgba) in frames.iter().enumerate() {
+        transparent |= Frame::new(width, height, rgba, i)?.has_transparency();
+    }
+    let frames = frames.iter().enumerate().map(|(i, rgba)| Frame {
+        width,
+        height,
+        rgba,
+        index: i,
+    });
+
+    writeln!(out, "/// Generated by arcadecoder_spritegen.")?;
+    writeln!(
+        out,
+        "pub static {name}: {SPRITE_PATH}::SpriteSheet = {SPRITE_PATH}::SpriteSheet::new("
+    )?;
+    writeln!(out, "    {width},")?;
+    writeln!(out, "    {height},")?;
+    writeln!(out, "    {},", frames.len())?;
+    writeln!(out, "    &[")?;
+    for frame in frames.clone() {
+        writeln!(out, "        // frame {}", frame.index)?;
+        write_bits(out, "        ", frame.color_bits())?;
+    }
+    writeln!(out, "    ],")?;
+
+    if transparent {
+        writeln!(out, ")")?;
+        writeln!(out, ".with_mask(&[")?;
+        for frame in frames {
+            writeln!(out, "    // frame {}", frame.index)?;
+            write_bits(out, "    ", frame.mask_bits())?;
+        }
+        writeln!(out, "]);")?;
+    } else {
+        writeln!(out, ");")?;
+    }
+    Ok(())
+}
+
+/// The pixels of one image, after they have been checked.
+struct Frame<'a> {
+    width: u8,
+    height: u8,
+    rgba: &'a [u8],
+    index: usize,
+}
+
+impl<'a> Frame<'a> {
+    /// Check that the pixels are the right length and every pixel can be shown.
+    fn new(width: u8, height: u8, rgba: &'a [u8], index: usize) -> Result<Self, Error> {
+        let frame = Self {
+            width,
+            height,
+            rgba,
+            index,
+        };
+        if rgba.len() != frame.pixel_count() * BYTES_PER_PIXEL {
+            return Err(Error::Length { frame: index });
+        }
+
+        for (i, pixel) in rgba.chunks_exact(BYTES_PER_PIXEL).enumerate() {
+            let display_color = pixel[..3].iter().all(|v| *v == 0x00 || *v == 0xff);
+            let valid = match pixel[3] {
+                0x00 => true,
+                0xff => display_color,
+                _ => false,
+            };
+            if !valid {
+                return Err(Error::Color {
+                    frame: index,
+                    x: i % usize::from(width),
+                    y: i / usize::from(width),
+                    rgba: [pixel[0], pixel[1], pixel[2], pixel[3]],
+                });
+            }
+        }
+        Ok(frame)
+    }
+
+    fn pixel_count(&self) -> usize {
+        usize::from(self.width) * usize::from(self.height)
+    }
+
+    /// Get whether each channel of a pixel is on, or `None` if it is transparent.
+    fn pixel(&self, index: usize) -> Option<[bool; 3]> {
+        let pixel = &self.rgba[index * BYTES_PER_PIXEL..][..BYTES_PER_PIXEL];
+        (pixel[3] != 0).then(|| [pixel[0] != 0, pixel[1] != 0, pixel[2] != 0])
+    }
+
+    fn has_transparency(&self) -> bool {
+        (0..self.pixel_count()).any(|i| self.pixel(i).is_none())
+    }
+
+    /// The bits of every pixel's color, with transparent pixels black.
+    fn color_bits(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.pixel_count()).flat_map(|i| self.pixel(i).unwrap_or_default())
+    }
+
+    /// A bit for every pixel that is set if the pixel is drawn.
+    fn mask_bits(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.pixel_count()).map(|i| self.pixel(i).is_some())
+    }
+}
+
+/// Check that an image can be stored as a sprite.
+fn check_size(width: usize, height: usize) -> Result<(u8, u8), Error> {
+    match (u8::try_from(width), u8::try_from(height)) {
+        (Ok(w @ 1..), Ok(h @ 1..)) => Ok((w, h)),
+        _ => Err(Error::Size { width, height }),
+    }
+}
+
+/// Write bits packed from the most significant bit of each byte, starting on a new byte and line.
+fn write_bits(
+    out: &mut impl Write,
+    indent: &str,
+    bits: impl Iterator<Item = bool>,
+) -> Result<(), Error> {
+    let mut byte = 0_u8;
+    let mut bit = 0;
+    let mut index = 0;
+    for on in bits {
+        if on {
+            byte |= 0x80 >> bit;
+        }
+        bit += 1;
+
+        if bit == 8 {
+            write_byte(out, indent, byte, index)?;
+            (byte, bit, index) = (0, 0, index + 1);
+        }
+    }
+    if bit > 0 {
+        write_byte(out, indent, byte, index)?;
+        index += 1;
+    }
+    if index > 0 {
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Write a packed byte, starting a new line every [`BYTES_PER_LINE`] bytes.
+fn write_byte(out: &mut impl Write, indent: &str, byte: u8, index: usize) -> Result<(), Error> {
+    let column = index % BYTES_PER_LINE;
+    if column == 0 {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        write!(out, "{indent}0x{byte:02x},")?;
+    } else {
+        write!(out, " 0x{byte:02x},")?;
+    }
+    Ok(())
+}
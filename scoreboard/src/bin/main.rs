@@ -2,8 +2,12 @@
 #![no_main]
 
 use arcadecoder_hw::{
-    font::{FONT_5X5, FONT_5X5_SIZE},
-    ArcadeCoder, ButtonEvent, Color, GREEN, MAGENTA, RED, WHITE,
+    arcade_coder_peripherals,
+    config::ArcadeCoderConfig,
+    font::FONT_5X5,
+    framebuffer::{Framebuffer, WIDTH},
+    text::{Alignment, Digits},
+    ArcadeCoder, ButtonEvent, Color, Coordinates, GREEN, MAGENTA, RED, WHITE,
 };
 use embassy_executor::Spawner;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel, mutex::Mutex};
@@ -18,6 +22,10 @@ use static_cell::StaticCell;
 
 const A_COLOR: Color = GREEN;
 const B_COLOR: Color = MAGENTA;
+// display brightness levels from 0 (off) to 255 (full)
+// hold both bottom corners to cycle through them, to use the scoreboard in a dark room. tapping one
+// bottom corner on its own still resets the scores
+const BRIGHTNESS_LEVELS: [u8; 4] = [255, 96, 32, 8];
 
 // mutexes and channels for sharing data between task and main thread
 static EVENT_CH: StaticCell<Channel<NoopRawMutex, ButtonEvent, 64>> = StaticCell::new();
@@ -36,6 +44,9 @@ struct State {
 
     a_winner: bool,
     b_winner: bool,
+    brightness_level: usize,
+    corners_held: [bool; 2],
+    corners_chorded: bool,
 }
 
 impl State {
@@ -47,6 +58,9 @@ impl State {
             win_diff: 2,
             a_winner: false,
             b_winner: false,
+            brightness_level: 0,
+            corners_held: [false, false],
+            corners_chorded: false,
         }
     }
 
@@ -86,6 +100,36 @@ impl State {
         self.score_b = self.score_b.checked_sub(1).unwrap_or(self.score_b);
         self.check_win();
     }
+
+    pub fn brightness(&self) -> u8 {
+        BRIGHTNESS_LEVELS[self.brightness_level]
+    }
+
+    // press one of the bottom corners, returning true if the brightness changed
+    pub fn press_corner(&mut self, right: bool) -> bool {
+        self.corners_held[usize::from(right)] = true;
+
+        // pressing the second corner while the first is held changes to the next brightness
+        if self.corners_held == [true, true] {
+            self.brightness_level = (self.brightness_level + 1) % BRIGHTNESS_LEVELS.len();
+            self.corners_chorded = true;
+            return true;
+        }
+        false
+    }
+
+    // release one of the bottom corners, returning true if it was tapped on its own
+    pub fn release_corner(&mut self, right: bool) -> bool {
+        if !core::mem::take(&mut self.corners_held[usize::from(right)]) {
+            return false;
+        }
+
+        let tapped = !self.corners_chorded;
+        if self.corners_held == [false, false] {
+            self.corners_chorded = false;
+        }
+        tapped
+    }
 }
 
 // lightweight snapshot type and helpers to avoid holding the mutex while rendering.
@@ -102,54 +146,52 @@ fn snapshot_from_state(s: &State) -> Snapshot {
 }
 
 // render a snapshot to the display
-fn render_snapshot(ac: &mut ArcadeCoder<'_>, snap: Snapshot) {
+fn render_snapshot(fb: &mut Framebuffer, snap: Snapshot) {
     let (score_a, score_b, win_threshold, a_winner, b_winner) = snap;
 
-    ac.clear();
+    fb.clear();
 
     let mut a_text_color = A_COLOR;
     let mut b_text_color = B_COLOR;
 
     if a_winner {
-        ac.draw_rect((0, 0), (11, 11), A_COLOR);
+        fb.draw_rect((0, 0), (11, 11), A_COLOR);
         a_text_color = WHITE;
     } else if b_winner {
-        ac.draw_rect((0, 0), (11, 11), B_COLOR);
+        fb.draw_rect((0, 0), (11, 11), B_COLOR);
         b_text_color = WHITE;
     }
 
-    ac.draw_digit(
-        (score_a % 10).into(),
-        FONT_5X5,
-        FONT_5X5_SIZE,
-        (0, 6),
-        a_text_color,
-    );
-    ac.draw_digit(
-        ((score_a / 10) % 10).into(),
-        FONT_5X5,
-        FONT_5X5_SIZE,
-        (0, 0),
-        a_text_color,
-    );
-    ac.draw_digit(
-        (score_b % 10).into(),
-        FONT_5X5,
-        FONT_5X5_SIZE,
-        (7, 6),
-        b_text_color,
-    );
-    ac.draw_digit(
-        ((score_b / 10) % 10).into(),
-        FONT_5X5,
-        FONT_5X5_SIZE,
-        (7, 0),
-        b_text_color,
-    );
+    draw_score(fb, score_a, (0, 0), Alignment::Left, a_text_color);
+    draw_score(fb, score_b, (WIDTH - 1, 0), Alignment::Right, b_text_color);
 
     if win_threshold == 21 {
-        ac.set_pixel((5, 11), RED);
-        ac.set_pixel((6, 11), RED);
+        fb.set_pixel((5, 11), RED);
+        fb.set_pixel((6, 11), RED);
+    }
+}
+
+// draw a score as two digits, tens above units, aligned to the left or right edge
+fn draw_score(
+    fb: &mut Framebuffer,
+    score: u8,
+    anchor: Coordinates,
+    alignment: Alignment,
+    color: Color,
+) {
+    let line_height = FONT_5X5.height() + 1;
+    let digits = [score / 10 % 10, score % 10];
+
+    for (line, digit) in digits.into_iter().enumerate() {
+        let pos = (anchor.0, anchor.1 + line * line_height);
+        fb.draw_number(
+            digit.into(),
+            Digits::Zeros(1),
+            &FONT_5X5,
+            pos,
+            alignment,
+            color,
+        );
     }
 }
 
@@ -169,7 +211,11 @@ async fn state_task(
 
             match event {
                 ButtonEvent::Pressed(x, y) => {
-                    if y == 11 && (x == 5 || x == 6) {
+                    if y == 11 && (x == 0 || x == 11) {
+                        // if a bottom corner, change the brightness when both are held. a corner on
+                        // its own resets the scores when released, so a chord does not reset them
+                        mutated = s.press_corner(x == 11);
+                    } else if y == 11 && (x == 5 || x == 6) {
                         if s.win_threshold == 11 {
                             s.win_threshold = 21
                         } else {
@@ -199,7 +245,13 @@ async fn state_task(
                         mutated = true;
                     }
                 }
-                ButtonEvent::Released(_, _) => {}
+                ButtonEvent::Released(x, y) => {
+                    if y == 11 && (x == 0 || x == 11) && s.release_corner(x == 11) {
+                        // if a bottom corner was tapped on its own, reset the scores
+                        s.reset();
+                        mutated = true;
+                    }
+                }
             }
 
             // if the state was mutated, trigger a redraw on the main thread
@@ -221,10 +273,9 @@ async fn main(spawner: Spawner) {
     esp_hal_embassy::init(timg0.timer0);
 
     // setup the arcade coder instance, passing in the required pins
-    let mut ac = ArcadeCoder::new(
-        p.SPI2, p.GPIO19, p.GPIO18, p.GPIO21, p.GPIO4, p.GPIO16, p.GPIO5, p.GPIO17, p.GPIO39,
-        p.GPIO36, p.GPIO35, p.GPIO34, p.GPIO33, p.GPIO32,
-    );
+    let mut ac =
+        ArcadeCoder::from_peripherals(arcade_coder_peripherals!(p), &ArcadeCoderConfig::default())
+            .expect("could not set up the arcade coder");
 
     // initialize mutexes and channels
     let ev: &'static Channel<NoopRawMutex, ButtonEvent, 64> = EVENT_CH.init(Channel::new());
@@ -243,14 +294,29 @@ async fn main(spawner: Spawner) {
     {
         let s = state_mutex.lock().await;
         let snap = snapshot_from_state(&s);
+        ac.brightness = s.brightness();
         drop(s);
-        render_snapshot(&mut ac, snap);
+        render_snapshot(ac.framebuffer.back_mut(), snap);
+        ac.framebuffer.present();
     }
 
     // main loop
+    let mut display_ok = true;
     loop {
-        // draw the display and get button press inputs
-        ac.scan();
+        // draw the display and get button press inputs, turning off the led while the display is failing
+        let scan_ok = match ac.try_scan() {
+            Ok(()) => true,
+            Err(e) => {
+                if display_ok {
+                    println!("Display error: {}", e);
+                }
+                false
+            }
+        };
+        if scan_ok != display_ok {
+            display_ok = scan_ok;
+            led.set_level(Level::from(scan_ok));
+        }
 
         // handle debounced button events from the library, passing through to the input channel
         ac.handle_input_events_to_channel(ev);
@@ -266,8 +332,10 @@ async fn main(spawner: Spawner) {
             // take a snapshot while holding the mutex, then render outside
             let s = state_mutex.lock().await;
             let snap = snapshot_from_state(&s);
+            ac.brightness = s.brightness();
             drop(s);
-            render_snapshot(&mut ac, snap);
+            render_snapshot(ac.framebuffer.back_mut(), snap);
+            ac.framebuffer.present();
         }
 
         Timer::after(Duration::from_millis(1)).await;